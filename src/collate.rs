@@ -0,0 +1,36 @@
+//! System calls for comparing strings according to the user’s locale.
+//!
+//! Collation is done by the C library’s `strcoll` function, which uses the
+//! collation rules for the current `LC_COLLATE` locale. Programs start off
+//! in the “C” locale, so the user’s locale has to be loaded from the
+//! environment with `setlocale` before any strings get compared.
+//!
+//! The collation order is needed when the user wants files sorted the same
+//! way that `ls` would sort them.
+
+use std::cmp::Ordering;
+use std::ffi::CString;
+use std::sync::{Once, ONCE_INIT};
+
+use libc::{c_char, setlocale, strcoll, LC_COLLATE};
+
+
+static LOAD_LOCALE: Once = ONCE_INIT;
+
+/// Compare two strings using the collation rules of the user’s locale.
+/// Strings that can’t be passed to C, because they contain a null byte,
+/// get compared byte-by-byte instead.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    LOAD_LOCALE.call_once(|| unsafe {
+        let empty = b"\0";
+        let _ = setlocale(LC_COLLATE, empty.as_ptr() as *const c_char);
+    });
+
+    match (CString::new(a), CString::new(b)) {
+        (Ok(a), Ok(b)) => {
+            let result = unsafe { strcoll(a.as_ptr(), b.as_ptr()) };
+            result.cmp(&0)
+        },
+        _ => a.cmp(b),
+    }
+}
@@ -1,8 +1,12 @@
 use ansi_term::Style;
 use ansi_term::Colour::{Red, Green, Yellow, Blue, Cyan, Purple, Fixed};
 
+use filetype::Classifier;
+use ls_colors::{parse_style, LsColors};
+use theme::{NameStyles, Theme};
 
-#[derive(Clone, Copy, Debug, Default, PartialEq)]
+
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Colours {
     pub filetypes:  FileTypes,
     pub perms:      Permissions,
@@ -20,6 +24,23 @@ pub struct Colours {
     pub symlink_path:     Style,
     pub broken_arrow:     Style,
     pub broken_filename:  Style,
+
+    /// Colours for file names from the `LS_COLORS` environment variable,
+    /// which take priority over the ones above.
+    pub ls_colors: LsColors,
+
+    /// The names of the categories whose colours have been set in
+    /// `EXA_COLORS` or a theme, such as `directory` or `image`. Files in
+    /// these categories don’t get coloured by `LS_COLORS` at all.
+    pub own_categories: Vec<&'static str>,
+
+    /// Colours for particular file names and extensions from a theme,
+    /// which take priority over all the others.
+    pub names: NameStyles,
+
+    /// The categories that decide the colours of file names from their
+    /// names, such as images or documents.
+    pub classifier: Classifier,
 }
 
 #[derive(Clone, Copy, Debug, Default, PartialEq)]
@@ -93,6 +114,128 @@ impl Colours {
         Colours::default()
     }
 
+    /// Override some of the styles with the entries from an `EXA_COLORS`
+    /// variable, such as `ur=1;33:sn=32:da=34`, where each key is the code
+    /// for one of the fields and each value is a list of ANSI style codes.
+    /// Styles for the types of files given this way also take priority
+    /// over any from `LS_COLORS`, for every file of that type: setting `im`
+    /// stops `*.png` from applying to images, and setting `sp` stops `pi`,
+    /// `so`, `bd`, and `cd` from applying to special files.
+    ///
+    /// Some keys mean different things in the two variables: `tw` is the
+    /// other-write permission bit here, but sticky, other-writable
+    /// directories in `LS_COLORS`.
+    ///
+    /// Returns the first entry that doesn’t make sense as an error, so
+    /// the user can find the mistake.
+    pub fn set_exa_colors(&mut self, input: &str) -> Result<(), String> {
+        for entry in input.split(':').filter(|e| !e.is_empty()) {
+            let mut parts = entry.splitn(2, '=');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key, value),
+                _                        => return Err(entry.to_owned()),
+            };
+
+            let style = match parse_style(value) {
+                Some(style)  => style,
+                None         => return Err(entry.to_owned()),
+            };
+
+            let class = match self.fields().into_iter().find(|&(k, _, _)| k == key) {
+                Some((_, class, field))  => { *field = style; class },
+                None                     => return Err(entry.to_owned()),
+            };
+
+            self.take_category(class);
+        }
+
+        Ok(())
+    }
+
+    /// Override some of the styles with the ones from a theme, where each
+    /// table is named after one of the groups of fields, and each key after
+    /// one of the fields in it. As with `EXA_COLORS`, file name styles given
+    /// this way take priority over any from `LS_COLORS`.
+    ///
+    /// Returns the first colour that doesn’t exist as an error.
+    pub fn set_theme(&mut self, theme: Theme) -> Result<(), String> {
+        for (table, key, style) in theme.styles {
+            let class = if table.is_empty() { key.clone() } else { format!("{}-{}", table, key) };
+
+            let class = match self.fields().into_iter().find(|&(_, c, _)| c == class) {
+                Some((_, class, field))  => { *field = style; class },
+                None                     => return Err(format!("unknown colour [{}] {}", table, key)),
+            };
+
+            self.take_category(class);
+        }
+
+        self.names = theme.names;
+        Ok(())
+    }
+
+    /// If the field with the given CSS class is the colour of one of the
+    /// categories of files, stop `LS_COLORS` from applying to that category.
+    fn take_category(&mut self, class: &'static str) {
+        if class.starts_with("filetypes-") {
+            self.own_categories.push(&class["filetypes-".len() ..]);
+        }
+    }
+
+    /// Every style, along with the key that sets it in `EXA_COLORS` and the
+    /// name of its CSS class.
+    fn fields(&mut self) -> Vec<(&'static str, &'static str, &mut Style)> {
+        vec![
+            ("fi", "filetypes-normal",          &mut self.filetypes.normal),
+            ("di", "filetypes-directory",       &mut self.filetypes.directory),
+            ("ln", "filetypes-symlink",         &mut self.filetypes.symlink),
+            ("sp", "filetypes-special",         &mut self.filetypes.special),
+            ("ex", "filetypes-executable",      &mut self.filetypes.executable),
+            ("im", "filetypes-image",           &mut self.filetypes.image),
+            ("vi", "filetypes-video",           &mut self.filetypes.video),
+            ("mu", "filetypes-music",           &mut self.filetypes.music),
+            ("lo", "filetypes-lossless",        &mut self.filetypes.lossless),
+            ("cr", "filetypes-crypto",          &mut self.filetypes.crypto),
+            ("do", "filetypes-document",        &mut self.filetypes.document),
+            ("co", "filetypes-compressed",      &mut self.filetypes.compressed),
+            ("tm", "filetypes-temp",            &mut self.filetypes.temp),
+            ("bu", "filetypes-immediate",       &mut self.filetypes.immediate),
+            ("cm", "filetypes-compiled",        &mut self.filetypes.compiled),
+            ("ur", "perms-user_read",           &mut self.perms.user_read),
+            ("uw", "perms-user_write",          &mut self.perms.user_write),
+            ("ux", "perms-user_execute_file",   &mut self.perms.user_execute_file),
+            ("ue", "perms-user_execute_other",  &mut self.perms.user_execute_other),
+            ("gr", "perms-group_read",          &mut self.perms.group_read),
+            ("gw", "perms-group_write",         &mut self.perms.group_write),
+            ("gx", "perms-group_execute",       &mut self.perms.group_execute),
+            ("tr", "perms-other_read",          &mut self.perms.other_read),
+            ("tw", "perms-other_write",         &mut self.perms.other_write),
+            ("tx", "perms-other_execute",       &mut self.perms.other_execute),
+            ("xa", "perms-attribute",           &mut self.perms.attribute),
+            ("sn", "size-numbers",              &mut self.size.numbers),
+            ("sb", "size-unit",                 &mut self.size.unit),
+            ("uu", "users-user_you",            &mut self.users.user_you),
+            ("un", "users-user_someone_else",   &mut self.users.user_someone_else),
+            ("gu", "users-group_yours",         &mut self.users.group_yours),
+            ("gn", "users-group_not_yours",     &mut self.users.group_not_yours),
+            ("lc", "links-normal",              &mut self.links.normal),
+            ("lm", "links-multi_link_file",     &mut self.links.multi_link_file),
+            ("ga", "git-new",                   &mut self.git.new),
+            ("gm", "git-modified",              &mut self.git.modified),
+            ("gd", "git-deleted",               &mut self.git.deleted),
+            ("gv", "git-renamed",               &mut self.git.renamed),
+            ("gt", "git-typechange",            &mut self.git.typechange),
+            ("xx", "punctuation",               &mut self.punctuation),
+            ("da", "date",                      &mut self.date),
+            ("in", "inode",                     &mut self.inode),
+            ("bl", "blocks",                    &mut self.blocks),
+            ("hd", "header",                    &mut self.header),
+            ("lp", "symlink_path",              &mut self.symlink_path),
+            ("ba", "broken_arrow",              &mut self.broken_arrow),
+            ("bf", "broken_filename",           &mut self.broken_filename),
+        ]
+    }
+
     pub fn colourful() -> Colours {
         Colours {
             filetypes: FileTypes {
@@ -160,7 +303,42 @@ impl Colours {
 
             symlink_path:     Cyan.normal(),
             broken_arrow:     Red.normal(),
-            broken_filename:  Red.underline()
+            broken_filename:  Red.underline(),
+
+            ls_colors: LsColors::default(),
+            own_categories: Vec::new(),
+            names: NameStyles::default(),
+            classifier: Classifier::default(),
         }
     }
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::Colours;
+    use ansi_term::Colour::{Green, Yellow};
+
+    #[test]
+    fn exa_colors() {
+        let mut colours = Colours::colourful();
+        assert_eq!(colours.set_exa_colors("ur=1;33:sn=32:"), Ok(()));
+        assert_eq!(colours.perms.user_read, Yellow.bold());
+        assert_eq!(colours.size.numbers, Green.normal());
+    }
+
+    #[test]
+    fn exa_colors_errors() {
+        let mut colours = Colours::colourful();
+        assert_eq!(colours.set_exa_colors("zz=1"), Err("zz=1".to_owned()));
+        assert_eq!(colours.set_exa_colors("ur=bold"), Err("ur=bold".to_owned()));
+        assert_eq!(colours.set_exa_colors("ur"), Err("ur".to_owned()));
+    }
+
+    #[test]
+    fn exa_colors_own_categories() {
+        let mut colours = Colours::colourful();
+        assert_eq!(colours.set_exa_colors("im=35:sp=33:ur=1"), Ok(()));
+        assert_eq!(colours.own_categories, vec![ "image", "special" ]);
+    }
+}
@@ -0,0 +1,228 @@
+//! Reading exa’s **configuration files**, such as themes.
+//!
+//! These are written in a small subset of TOML: tables, comments, and keys
+//! whose values are either strings in double quotes, or lists of them in
+//! square brackets, all on one line.
+
+use std::env::var_os;
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+
+/// The directory that exa’s configuration lives in, which is `exa` inside
+/// either `$XDG_CONFIG_HOME` or `~/.config`.
+pub fn directory() -> Option<PathBuf> {
+    if let Some(config) = var_os("XDG_CONFIG_HOME") {
+        if !config.is_empty() {
+            return Some(PathBuf::from(config).join("exa"));
+        }
+    }
+
+    var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("exa"))
+}
+
+/// Read the contents of a file, returning `None` if it doesn’t exist, and
+/// an error with its path in it if it can’t be read.
+pub fn read(path: &Path) -> Result<Option<String>, String> {
+    let mut contents = String::new();
+
+    match fs::File::open(path).and_then(|mut f| f.read_to_string(&mut contents)) {
+        Ok(_)                                               => Ok(Some(contents)),
+        Err(ref e) if e.kind() == io::ErrorKind::NotFound   => Ok(None),
+        Err(e)                                              => Err(format!("{}: {}", path.display(), e)),
+    }
+}
+
+
+/// The value of one of the keys.
+#[derive(PartialEq, Debug, Clone)]
+pub enum Value {
+    String(String),
+    List(Vec<String>),
+}
+
+/// One of the keys in a file, along with which table it’s in (which is
+/// empty for the keys before the first table) and the number of its line.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Entry {
+    pub line:   usize,
+    pub table:  String,
+    pub key:    String,
+    pub value:  Value,
+}
+
+impl Entry {
+
+    /// An error message pointing at this entry’s line.
+    pub fn error(&self, problem: &str) -> String {
+        format!("line {}: {}", self.line, problem)
+    }
+}
+
+/// Parse the contents of a configuration file into its entries, in the
+/// order they were written. Returns the number of the first line that
+/// can’t be parsed, along with what’s wrong with it, as an error.
+pub fn parse(input: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    let mut table = String::new();
+
+    for (number, line) in input.lines().enumerate() {
+        let line = strip_comment(line).trim();
+        let error = |problem: &str| Err(format!("line {}: {}", number + 1, problem));
+
+        if line.is_empty() {
+            continue;
+        }
+        else if line.starts_with('[') {
+            if !line.ends_with(']') {
+                return error("table name isn’t closed");
+            }
+
+            table = line[1 .. line.len() - 1].trim().to_owned();
+            continue;
+        }
+
+        let equals = match line.find('=') {
+            Some(index) => index,
+            None        => return error("expected key = \"value\""),
+        };
+
+        let key = match parse_key(&line[.. equals]) {
+            Some(key) => key,
+            None      => return error("invalid key"),
+        };
+
+        let value = match parse_value(&line[equals + 1 ..]) {
+            Some(value) => value,
+            None        => return error("invalid value"),
+        };
+
+        entries.push(Entry { line: number + 1, table: table.clone(), key: key, value: value });
+    }
+
+    Ok(entries)
+}
+
+/// Remove the comment from the end of a line, if it has one, leaving any
+/// `#` characters in strings alone.
+fn strip_comment(line: &str) -> &str {
+    let mut in_string = false;
+    let mut escaped = false;
+
+    for (index, c) in line.char_indices() {
+        match c {
+            _ if escaped          => escaped = false,
+            '\\' if in_string     => escaped = true,
+            '"'                   => in_string = !in_string,
+            '#' if !in_string     => return &line[.. index],
+            _                     => {},
+        }
+    }
+
+    line
+}
+
+/// Parse a key, which is either a quoted string, or a bare word.
+fn parse_key(input: &str) -> Option<String> {
+    let input = input.trim();
+
+    if input.starts_with('"') {
+        match take_string(input) {
+            Some((key, rest)) if rest.trim().is_empty() => Some(key),
+            _                                           => None,
+        }
+    }
+    else if input.is_empty() || input.contains(char::is_whitespace) {
+        None
+    }
+    else {
+        Some(input.to_owned())
+    }
+}
+
+/// Parse a value, which is either a string, or a list of strings separated
+/// by commas.
+fn parse_value(input: &str) -> Option<Value> {
+    let input = input.trim();
+
+    if input.starts_with('[') && input.ends_with(']') {
+        let mut strings = Vec::new();
+        let mut rest = input[1 .. input.len() - 1].trim();
+
+        while !rest.is_empty() {
+            let (string, after) = match take_string(rest) {
+                Some(pair) => pair,
+                None       => return None,
+            };
+
+            strings.push(string);
+            rest = after.trim();
+
+            if rest.starts_with(',') {
+                rest = rest[1..].trim();
+            }
+            else if !rest.is_empty() {
+                return None;
+            }
+        }
+
+        Some(Value::List(strings))
+    }
+    else {
+        match take_string(input) {
+            Some((string, rest)) if rest.trim().is_empty() => Some(Value::String(string)),
+            _                                              => None,
+        }
+    }
+}
+
+/// Read a string in double quotes from the start of the input, which can
+/// have quotes and backslashes in it escaped with backslashes, returning
+/// it along with the rest of the input.
+fn take_string(input: &str) -> Option<(String, &str)> {
+    if !input.starts_with('"') {
+        return None;
+    }
+
+    let mut string = String::new();
+    let mut chars = input[1..].char_indices();
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '\\' => match chars.next() {
+                Some((_, c @ '"')) | Some((_, c @ '\\'))  => string.push(c),
+                _                                         => return None,
+            },
+            '"'  => return Some((string, &input[index + 2 ..])),
+            c    => string.push(c),
+        }
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{parse, Entry, Value};
+
+    #[test]
+    fn tables() {
+        let entries = parse("top = \"1\"  # one\n\n[table]\n\"quoted key\" = [ \"a\", \"b#\" ]\n").unwrap();
+        assert_eq!(entries, vec![
+            Entry { line: 1, table: String::new(), key: "top".to_owned(), value: Value::String("1".to_owned()) },
+            Entry { line: 4, table: "table".to_owned(), key: "quoted key".to_owned(), value: Value::List(vec![ "a".to_owned(), "b#".to_owned() ]) },
+        ])
+    }
+
+    #[test]
+    fn escapes() {
+        let entries = parse("key = \"a \\\"b\\\" c\"").unwrap();
+        assert_eq!(entries[0].value, Value::String("a \"b\" c".to_owned()))
+    }
+
+    #[test]
+    fn bad_line() {
+        assert_eq!(parse("[table]\nkey = bold").unwrap_err(), "line 2: invalid value")
+    }
+}
@@ -2,6 +2,7 @@ use std::io::{self, Result as IOResult};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::slice::Iter as SliceIter;
+use std::sync::Arc;
 
 use feature::Git;
 use file::{File, fields};
@@ -22,8 +23,10 @@ pub struct Dir {
     pub path: PathBuf,
 
     /// Holds a `Git` object if scanning for Git repositories is switched on,
-    /// and this directory happens to contain one.
-    git: Option<Git>,
+    /// and this directory happens to contain one. It's shared between a
+    /// directory and any subdirectories that get read from it, so a
+    /// repository only gets scanned once per recursive listing.
+    git: Option<Arc<Git>>,
 }
 
 impl Dir {
@@ -33,22 +36,42 @@ impl Dir {
     /// isn't actually a directory, or if there's an IO error that occurs
     /// while scanning.
     pub fn read_dir(path: &Path, git: bool) -> IOResult<Dir> {
+        let git = if git { Git::scan(path).ok().map(Arc::new) } else { None };
+        Dir::read_dir_with_git(path, git)
+    }
+
+    /// Create a new Dir object for a subdirectory of this one. If this
+    /// directory has already had its Git repository scanned, the subdirectory
+    /// shares it, rather than discovering and scanning it all over again.
+    pub fn read_subdir(&self, path: &Path, git: bool) -> IOResult<Dir> {
+        match self.git {
+            Some(ref g) if git  => Dir::read_dir_with_git(path, Some(g.clone())),
+            _                   => Dir::read_dir(path, git),
+        }
+    }
+
+    fn read_dir_with_git(path: &Path, git: Option<Arc<Git>>) -> IOResult<Dir> {
         let reader = try!(fs::read_dir(path));
         let contents = try!(reader.map(|e| e.map(|e| e.path())).collect());
 
         Ok(Dir {
             contents: contents,
             path: path.to_path_buf(),
-            git: if git { Git::scan(path).ok() } else { None },
+            git: git,
         })
     }
 
     /// Produce an iterator of IO results of trying to read all the files in
-    /// this directory.
-    pub fn files<'dir>(&'dir self) -> Files<'dir> {
+    /// this directory, starting with the `.` and `..` entries if the given
+    /// dot filter asks for them.
+    pub fn files<'dir>(&'dir self, dots: DotFilter) -> Files<'dir> {
         Files {
             inner: self.contents.iter(),
             dir: &self,
+            dots: match dots {
+                DotFilter::DotfilesAndDots  => Dots::DotNext,
+                _                           => Dots::FilesNext,
+            },
         }
     }
 
@@ -75,6 +98,15 @@ impl Dir {
             (&None, _)               => fields::Git::empty()
         }
     }
+
+    /// Whether the given file is ignored by this directory's Git repository.
+    /// Files outside of a repository are never ignored.
+    pub fn git_ignored(&self, path: &Path) -> bool {
+        match self.git {
+            Some(ref git)  => git.is_ignored(path),
+            None           => false,
+        }
+    }
 }
 
 
@@ -82,12 +114,66 @@ impl Dir {
 pub struct Files<'dir> {
     inner: SliceIter<'dir, PathBuf>,
     dir: &'dir Dir,
+
+    /// Which of the `.` and `..` pseudo-entries still need to be produced,
+    /// before moving on to the actual contents of the directory.
+    dots: Dots,
+}
+
+impl<'dir> Files<'dir> {
+    fn dot_entry(&self, path: PathBuf, name: &str) -> Result<File<'dir>, (PathBuf, io::Error)> {
+        File::for_dots(&path, self.dir, name).map_err(|t| (path, t))
+    }
 }
 
 impl<'dir> Iterator for Files<'dir> {
     type Item = Result<File<'dir>, (PathBuf, io::Error)>;
 
     fn next(&mut self) -> Option<Self::Item> {
-        self.inner.next().map(|path| File::from_path(path, Some(self.dir)).map_err(|t| (path.clone(), t)))
+        match self.dots {
+            Dots::DotNext => {
+                self.dots = Dots::DotDotNext;
+                Some(self.dot_entry(self.dir.path.clone(), "."))
+            },
+            Dots::DotDotNext => {
+                self.dots = Dots::FilesNext;
+                Some(self.dot_entry(self.dir.join(Path::new("..")), ".."))
+            },
+            Dots::FilesNext => {
+                self.inner.next().map(|path| File::from_path(path, Some(self.dir)).map_err(|t| (path.clone(), t)))
+            },
+        }
+    }
+}
+
+
+/// The dot directories that need to be listed before the files in a
+/// directory, which get produced in this order.
+enum Dots {
+    DotNext,
+    DotDotNext,
+    FilesNext,
+}
+
+
+/// Which hidden files should be listed, based on how many times the user
+/// passed the `--all` option.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum DotFilter {
+
+    /// Hide every file beginning with a dot. This is the default.
+    JustFiles,
+
+    /// Show dotfiles, but not the `.` and `..` entries. (`-a`)
+    Dotfiles,
+
+    /// Show dotfiles, *and* the `.` and `..` entries, like `ls -a` does.
+    /// (`-aa`)
+    DotfilesAndDots,
+}
+
+impl Default for DotFilter {
+    fn default() -> DotFilter {
+        DotFilter::JustFiles
     }
 }
\ No newline at end of file
@@ -8,6 +8,11 @@ use file::fields;
 /// Container of Git statuses for all the files in this folder's Git repository.
 pub struct Git {
     statuses: Vec<(PathBuf, git2::Status)>,
+
+    /// The paths of the files and directories that the repository ignores,
+    /// kept apart from the rest so they don't have to be searched for
+    /// among every status.
+    ignored: Vec<PathBuf>,
 }
 
 impl Git {
@@ -18,14 +23,19 @@ impl Git {
         let repo = try!(git2::Repository::discover(path));
         let workdir = match repo.workdir() {
             Some(w) => w,
-            None => return Ok(Git { statuses: vec![] }),  // bare repo
+            None => return Ok(Git { statuses: vec![], ignored: vec![] }),  // bare repo
         };
 
-        let statuses = try!(repo.statuses(None)).iter()
+        let statuses: Vec<(PathBuf, git2::Status)> = try!(repo.statuses(None)).iter()
                                                 .map(|e| (workdir.join(Path::new(e.path().unwrap())), e.status()))
                                                 .collect();
 
-        Ok(Git { statuses: statuses })
+        let ignored = statuses.iter()
+                              .filter(|p| p.1.contains(git2::STATUS_IGNORED))
+                              .map(|p| p.0.clone())
+                              .collect();
+
+        Ok(Git { statuses: statuses, ignored: ignored })
     }
 
     /// Get the status for the file at the given path, if present.
@@ -48,6 +58,13 @@ impl Git {
 
         fields::Git { staged: index_status(s), unstaged: working_tree_status(s) }
     }
+
+    /// Whether the file at the given path is ignored by the repository. The
+    /// statuses only list the topmost ignored directory, rather than every
+    /// file underneath it, so anything inside an ignored path counts too.
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        self.ignored.iter().any(|p| path.starts_with(p))
+    }
 }
 
 /// The character to display if the file has been modified, but not staged.
@@ -74,3 +91,63 @@ fn index_status(status: git2::Status) -> fields::GitStatus {
         _                                               => fields::GitStatus::NotModified,
     }
 }
+
+
+#[cfg(test)]
+mod test {
+    use super::Git;
+    use git2;
+    use dir::{Dir, DotFilter};
+    use fixture::Fixture;
+
+    /// Make a repository, with the given fixture name, that ignores a build
+    /// directory and log files, with a file inside the build directory, and
+    /// a sibling directory whose name only starts with the ignored one’s.
+    fn repository(name: &str) -> Fixture {
+        let fixture = Fixture::new(name);
+        let _ = git2::Repository::init(fixture.path()).unwrap();
+        let _ = fixture.file(".gitignore", b"/target\n*.log\n");
+        let _ = fixture.dir("target/debug");
+        let _ = fixture.file("target/debug/exa", b"");
+        let _ = fixture.dir("targets");
+        let _ = fixture.file("targets/list", b"");
+        let _ = fixture.file("build.log", b"");
+        let _ = fixture.file("README.md", b"");
+        fixture
+    }
+
+    #[test]
+    fn ignored_paths() {
+        let fixture = repository("git-ignored-paths");
+        let git = Git::scan(fixture.path()).unwrap();
+
+        assert!(git.is_ignored(&fixture.path().join("build.log")));
+        assert!(git.is_ignored(&fixture.path().join("target")));
+        assert!(git.is_ignored(&fixture.path().join("target/debug/exa")));
+        assert!(!git.is_ignored(&fixture.path().join("targets")));
+        assert!(!git.is_ignored(&fixture.path().join("targets/list")));
+        assert!(!git.is_ignored(&fixture.path().join("README.md")));
+    }
+
+    #[test]
+    fn ignored_files() {
+        let fixture = repository("git-ignored-files");
+        let dir = Dir::read_dir(fixture.path(), true).unwrap();
+
+        let mut ignored: Vec<String> = dir.files(DotFilter::Dotfiles)
+                                          .filter_map(Result::ok)
+                                          .filter(|f| f.is_git_ignored())
+                                          .map(|f| f.name)
+                                          .collect();
+        ignored.sort();
+
+        assert_eq!(ignored, vec![ "build.log", "target" ]);
+    }
+
+    #[test]
+    fn ignored_files_without_git() {
+        let fixture = repository("git-ignored-files-without-git");
+        let dir = Dir::read_dir(fixture.path(), false).unwrap();
+        assert!(dir.files(DotFilter::Dotfiles).filter_map(Result::ok).all(|f| !f.is_git_ignored()));
+    }
+}
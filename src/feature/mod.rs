@@ -23,4 +23,8 @@ impl Git {
     pub fn dir_status(&self, path: &Path) -> fields::Git {
         self.status(path)
     }
+
+    pub fn is_ignored(&self, _: &Path) -> bool {
+        panic!("Tried to access a Git repo without Git support!");
+    }
 }
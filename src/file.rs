@@ -1,15 +1,16 @@
 //! Files, and methods and fields to access their metadata.
 
 use std::ascii::AsciiExt;
+use std::collections::HashMap;
 use std::env::current_dir;
 use std::fs;
-use std::io::Result as IOResult;
-use std::os::unix::fs::{MetadataExt, PermissionsExt};
+use std::io::{Read, Result as IOResult};
+use std::ops::Add;
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
 use std::path::{Component, Path, PathBuf};
 
-use unicode_width::UnicodeWidthStr;
-
 use dir::Dir;
+use magic::{Magic, SNIFF_LENGTH};
 
 use self::fields as f;
 
@@ -80,6 +81,21 @@ pub struct File<'dir> {
     /// contain a reference to it, which is used in certain operations (such
     /// as looking up a file's Git status).
     pub dir: Option<&'dir Dir>,
+
+    /// Whether this is one of the `.` or `..` pseudo-entries that get listed
+    /// when using `--all` twice. These refer back to directories that are
+    /// already being listed, so they must never be recursed into.
+    pub is_all_all: bool,
+
+    /// The total size of everything inside this directory, if it's been
+    /// calculated. This is only done when the user asks for it, as it means
+    /// reading every file in the directory's subtree.
+    pub total_size: Option<u64>,
+
+    /// The format of this file, going by the first few bytes of its
+    /// contents, if it's been sniffed. Like the total size, this is only
+    /// done when the user asks for it, as it means reading every file.
+    pub magic: Option<Magic>,
 }
 
 impl<'dir> File<'dir> {
@@ -98,14 +114,36 @@ impl<'dir> File<'dir> {
         let filename = path_filename(path);
 
         File {
-            path:      path.to_path_buf(),
-            dir:       parent,
-            metadata:  metadata,
-            ext:       ext(&filename),
-            name:      filename.to_string(),
+            path:        path.to_path_buf(),
+            dir:         parent,
+            metadata:    metadata,
+            ext:         ext(&filename),
+            name:        filename.to_string(),
+            is_all_all:  false,
+            total_size:  None,
+            magic:       None,
         }
     }
 
+    /// Create a new File object for one of the `.` or `..` entries of the
+    /// given directory. These get their name passed in, rather than having
+    /// it extracted from the path, as the path's last component is usually
+    /// the name of the directory itself.
+    pub fn for_dots(path: &Path, parent: &'dir Dir, name: &str) -> IOResult<File<'dir>> {
+        let metadata = try!(fs::symlink_metadata(path));
+
+        Ok(File {
+            path:        path.to_path_buf(),
+            dir:         Some(parent),
+            metadata:    metadata,
+            ext:         None,
+            name:        name.to_string(),
+            is_all_all:  true,
+            total_size:  None,
+            magic:       None,
+        })
+    }
+
     /// Whether this file is a directory on the filesystem.
     pub fn is_directory(&self) -> bool {
         self.metadata.is_dir()
@@ -118,7 +156,10 @@ impl<'dir> File<'dir> {
     /// Returns an IO error upon failure, but this shouldn't be used to check
     /// if a `File` is a directory or not! For that, just use `is_directory()`.
     pub fn to_dir(&self, scan_for_git: bool) -> IOResult<Dir> {
-        Dir::read_dir(&*self.path, scan_for_git)
+        match self.dir {
+            Some(parent)  => parent.read_subdir(&*self.path, scan_for_git),
+            None          => Dir::read_dir(&*self.path, scan_for_git),
+        }
     }
 
     /// Whether this file is a regular file on the filesystem - that is, not a
@@ -142,7 +183,18 @@ impl<'dir> File<'dir> {
 
     /// Whether this file is a named pipe on the filesystem.
     pub fn is_pipe(&self) -> bool {
-        false  // TODO: Still waiting on this one...
+        self.metadata.file_type().is_fifo()
+    }
+
+    /// Whether this file is a Unix domain socket on the filesystem.
+    pub fn is_socket(&self) -> bool {
+        self.metadata.file_type().is_socket()
+    }
+
+    /// Whether this file is a block or character device on the filesystem.
+    pub fn is_device(&self) -> bool {
+        let file_type = self.metadata.file_type();
+        file_type.is_block_device() || file_type.is_char_device()
     }
 
     /// Whether this file is a dotfile, based on its name. In Unix, file names
@@ -180,15 +232,6 @@ impl<'dir> File<'dir> {
         path_prefix
     }
 
-    /// The Unicode 'display width' of the filename.
-    ///
-    /// This is related to the number of graphemes in the string: most
-    /// characters are 1 columns wide, but in some contexts, certain
-    /// characters are actually 2 columns wide.
-    pub fn file_name_width(&self) -> usize {
-        UnicodeWidthStr::width(&self.name[..])
-    }
-
     /// Assuming the current file is a symlink, follows the link and
     /// returns a File object from the path the link points to.
     ///
@@ -211,11 +254,14 @@ impl<'dir> File<'dir> {
         // Use plain `metadata` instead of `symlink_metadata` - we *want* to follow links.
         if let Ok(metadata) = fs::metadata(&target_path) {
             Ok(File {
-                path:      target_path.to_path_buf(),
-                dir:       self.dir,
-                metadata:  metadata,
-                ext:       ext(&filename),
-                name:      filename.to_string(),
+                path:        target_path.to_path_buf(),
+                dir:         self.dir,
+                metadata:    metadata,
+                ext:         ext(&filename),
+                name:        filename.to_string(),
+                is_all_all:  false,
+                total_size:  None,
+                magic:       None,
             })
         }
         else {
@@ -256,6 +302,39 @@ impl<'dir> File<'dir> {
         }
     }
 
+    /// Work out the total size of this directory: its own size, plus the
+    /// sizes of everything underneath it. This is either the *apparent* size
+    /// of the files, or the space they take up on disk, going by the number
+    /// of blocks they use.
+    ///
+    /// Symlinks are never followed, so there's no way to get stuck in a
+    /// loop, and files with several hard links only get counted once.
+    /// Directories that can't be read, such as because of their
+    /// permissions, get skipped over, so the total may be an underestimate.
+    ///
+    /// The total size of each directory underneath this one gets worked out
+    /// along the way, and these are put in the given map by their paths, so
+    /// a tree doesn't have to walk each subtree again.
+    pub fn calculate_total_size(&self, on_disk: bool, subtrees: &mut HashMap<PathBuf, u64>) -> u64 {
+        let contents = subtree_size(&self.path, on_disk, subtrees);
+        entry_size(&self.metadata, on_disk) + contents.total()
+    }
+
+    /// Work out the format of this file by reading the start of it, if
+    /// it's a regular file in one of the formats that can be detected.
+    /// Files that can't be read don't get a format.
+    pub fn sniff(&self) -> Option<Magic> {
+        if !self.is_file() {
+            return None;
+        }
+
+        let mut bytes = Vec::new();
+        match fs::File::open(&self.path).and_then(|f| f.take(SNIFF_LENGTH).read_to_end(&mut bytes)) {
+            Ok(_)   => Magic::from_bytes(&bytes),
+            Err(_)  => None,
+        }
+    }
+
     /// The ID of the user that own this file.
     pub fn user(&self) -> f::User {
         f::User(self.metadata.uid())
@@ -270,10 +349,14 @@ impl<'dir> File<'dir> {
     ///
     /// For directories, no size is given. Although they do have a size on
     /// some filesystems, I've never looked at one of those numbers and gained
-    /// any information from it. So it's going to be hidden instead.
+    /// any information from it. So it's going to be hidden instead, unless
+    /// the total size of its contents has been calculated.
     pub fn size(&self) -> f::Size {
         if self.is_directory() {
-            f::Size::None
+            match self.total_size {
+                Some(total) => f::Size::Some(total),
+                None        => f::Size::None,
+            }
         }
         else {
             f::Size::Some(self.metadata.len())
@@ -289,7 +372,7 @@ impl<'dir> File<'dir> {
     }
 
     pub fn accessed_time(&self) -> f::Time {
-        f::Time(self.metadata.mtime())
+        f::Time(self.metadata.atime())
     }
 
     /// This file's 'type'.
@@ -373,22 +456,6 @@ impl<'dir> File<'dir> {
         }
     }
 
-    /// Whether this file's extension is any of the strings that get passed in.
-    ///
-    /// This will always return `false` if the file has no extension.
-    pub fn extension_is_one_of(&self, choices: &[&str]) -> bool {
-        match self.ext {
-            Some(ref ext)  => choices.contains(&&ext[..]),
-            None           => false,
-        }
-    }
-
-    /// Whether this file's name, including extension, is any of the strings
-    /// that get passed in.
-    pub fn name_is_one_of(&self, choices: &[&str]) -> bool {
-        choices.contains(&&self.name[..])
-    }
-
     /// This file's Git status as two flags: one for staged changes, and the
     /// other for unstaged changes.
     ///
@@ -398,14 +465,28 @@ impl<'dir> File<'dir> {
     pub fn git_status(&self) -> f::Git {
         match self.dir {
             None    => f::Git { staged: f::GitStatus::NotModified, unstaged: f::GitStatus::NotModified },
-            Some(d) => {
-                let cwd = match current_dir() {
-                    Err(_)  => Path::new(".").join(&self.path),
-                    Ok(dir) => dir.join(&self.path),
-                };
-
-                d.git_status(&cwd, self.is_directory())
-            },
+            Some(d) => d.git_status(&self.absolute_path(), self.is_directory()),
+        }
+    }
+
+    /// Whether this file is ignored by the Git repository it's in, going by
+    /// its `.gitignore` files and any other exclude rules.
+    ///
+    /// Like the Git status, this requires the file's parent directory, so
+    /// files passed in on the command line are never ignored.
+    pub fn is_git_ignored(&self) -> bool {
+        match self.dir {
+            None    => false,
+            Some(d) => d.git_ignored(&self.absolute_path()),
+        }
+    }
+
+    /// This file's path, joined onto the current directory, which is how Git
+    /// repositories refer to the paths inside them.
+    fn absolute_path(&self) -> PathBuf {
+        match current_dir() {
+            Err(_)  => Path::new(".").join(&self.path),
+            Ok(dir) => dir.join(&self.path),
         }
     }
 }
@@ -423,6 +504,74 @@ fn path_filename(path: &Path) -> String {
     }
 }
 
+/// The size of a single entry, either in bytes or in blocks taken up.
+fn entry_size(metadata: &fs::Metadata, on_disk: bool) -> u64 {
+    if on_disk {
+        metadata.blocks() * 512
+    }
+    else {
+        metadata.len()
+    }
+}
+
+/// The sizes of everything underneath a directory. Hard-linked files are
+/// kept apart by their device and inode numbers, so a file linked from two
+/// places in a subtree only gets counted once in it.
+#[derive(Default)]
+struct SubtreeSize {
+    unlinked: u64,
+    linked: HashMap<(u64, u64), u64>,
+}
+
+impl SubtreeSize {
+    fn total(&self) -> u64 {
+        self.unlinked + self.linked.values().fold(0, Add::add)
+    }
+}
+
+/// The total size of everything underneath the given directory, adding the
+/// total size of each directory in it to the given map along the way.
+fn subtree_size(path: &Path, on_disk: bool, subtrees: &mut HashMap<PathBuf, u64>) -> SubtreeSize {
+    let mut size = SubtreeSize::default();
+
+    let entries = match fs::read_dir(path) {
+        Ok(entries)  => entries,
+        Err(_)       => return size,
+    };
+
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry)  => entry,
+            Err(_)     => continue,
+        };
+
+        // This doesn't follow symlinks, in the same way that
+        // `symlink_metadata` doesn't.
+        let metadata = match entry.metadata() {
+            Ok(metadata)  => metadata,
+            Err(_)        => continue,
+        };
+
+        if metadata.is_dir() {
+            let path = entry.path();
+            let contents = subtree_size(&path, on_disk, subtrees);
+            let _ = subtrees.insert(path, entry_size(&metadata, on_disk) + contents.total());
+
+            size.unlinked += entry_size(&metadata, on_disk) + contents.unlinked;
+            size.linked.extend(contents.linked);
+        }
+        else if metadata.nlink() > 1 {
+            let _ = size.linked.insert((metadata.dev(), metadata.ino()), entry_size(&metadata, on_disk));
+        }
+        else {
+            size.unlinked += entry_size(&metadata, on_disk);
+        }
+    }
+
+    size
+}
+
+
 /// Extract an extension from a string, if one is present, in lowercase.
 ///
 /// The extension is the series of characters after the last dot. This
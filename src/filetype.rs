@@ -1,122 +1,467 @@
+//! Working out the **category** of a file, which decides what colour its
+//! name gets painted in.
+//!
+//! Apart from directories, symlinks, special files, and executables, this
+//! is worked out from the file’s name by a `Classifier`, which holds a list
+//! of categories along with the extensions, names, and glob patterns of the
+//! files in them. There’s a built-in set of categories, which can be added
+//! to, and given new categories, by a `categories.toml` file in exa’s
+//! configuration directory, and then by a `.exa-categories.toml` file in
+//! the current working directory, for settings that only apply to one
+//! project. This is the directory exa is run from, not the one being listed:
+//!
+//! ```toml
+//! [immediate]
+//! names = [ "justfile" ]
+//!
+//! [source]
+//! colour = "yellow"
+//! extensions = [ "rs", "c", "h" ]
+//! globs = [ "*.min.js" ]
+//! ```
+//!
+//! New categories get checked before the built-in ones, so they can take
+//! files away from them.
+//!
+//! A mistake in the user’s own file stops exa with an error, but one in a
+//! project’s file only gets warned about, as the project may well be
+//! someone else’s.
+
+use std::ascii::AsciiExt;
+use std::io::{self, Write};
+use std::path::Path;
+
 use ansi_term::Style;
+use glob;
 
-use file::File;
 use colours::Colours;
+use config::{self, Value};
+use file::File;
+use theme::parse_style;
 
 
-pub fn file_colour(colours: &Colours, file: &File) -> Style {
-    match file {
-        f if f.is_directory()        => colours.filetypes.directory,
-        f if f.is_executable_file()  => colours.filetypes.executable,
-        f if f.is_link()             => colours.filetypes.symlink,
-        f if !f.is_file()            => colours.filetypes.special,
-        f if f.is_immediate()        => colours.filetypes.immediate,
-        f if f.is_image()            => colours.filetypes.image,
-        f if f.is_video()            => colours.filetypes.video,
-        f if f.is_music()            => colours.filetypes.music,
-        f if f.is_lossless()         => colours.filetypes.lossless,
-        f if f.is_crypto()           => colours.filetypes.crypto,
-        f if f.is_document()         => colours.filetypes.document,
-        f if f.is_compressed()       => colours.filetypes.compressed,
-        f if f.is_temp()             => colours.filetypes.temp,
-        f if f.is_compiled()         => colours.filetypes.compiled,
-        _                            => colours.filetypes.normal,
+/// The category of a file.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Category<'c> {
+    Directory,
+    Executable,
+    Symlink,
+    Special,
+    Named(&'c FileCategory),
+    Normal,
+}
+
+impl<'c> Category<'c> {
+
+    /// The name of this category. The names of the built-in categories are
+    /// the same as their fields in `Colours`.
+    pub fn name(&self) -> &'c str {
+        match *self {
+            Category::Directory   => "directory",
+            Category::Executable  => "executable",
+            Category::Symlink     => "symlink",
+            Category::Special     => "special",
+            Category::Named(c)    => &c.name,
+            Category::Normal      => "normal",
+        }
+    }
+
+    /// The style that files in this category get painted with.
+    pub fn colour(&self, colours: &Colours) -> Style {
+        match *self {
+            Category::Directory   => colours.filetypes.directory,
+            Category::Executable  => colours.filetypes.executable,
+            Category::Symlink     => colours.filetypes.symlink,
+            Category::Special     => colours.filetypes.special,
+            Category::Named(c)    => c.colour(colours),
+            Category::Normal      => colours.filetypes.normal,
+        }
     }
 }
 
 
-trait FileTypes {
-    fn is_immediate(&self) -> bool;
-    fn is_image(&self) -> bool;
-    fn is_video(&self) -> bool;
-    fn is_music(&self) -> bool;
-    fn is_lossless(&self) -> bool;
-    fn is_crypto(&self) -> bool;
-    fn is_document(&self) -> bool;
-    fn is_compressed(&self) -> bool;
-    fn is_temp(&self) -> bool;
-    fn is_compiled(&self) -> bool;
+/// A category that files get put in based on their names.
+#[derive(PartialEq, Debug, Clone)]
+pub struct FileCategory {
+    pub name: String,
+
+    /// The style for this category’s files, which is only set for the ones
+    /// that don’t have a field in `Colours`, or have been given a new one.
+    style: Option<Style>,
+
+    /// The patterns that put a file in this category, if any of them match.
+    patterns: Vec<Pattern>,
 }
 
-impl<'_> FileTypes for File<'_> {
-    fn is_immediate(&self) -> bool {
-        self.name.starts_with("README") || self.name_is_one_of( &[
-            "Makefile", "Cargo.toml", "SConstruct", "CMakeLists.txt",
-            "build.gradle", "Rakefile", "Gruntfile.js",
-            "Gruntfile.coffee",
-        ])
-    }
+impl FileCategory {
+    fn new(name: &str, extensions: &[&str], names: &[&str], globs: &[&str]) -> FileCategory {
+        let mut patterns = Vec::new();
+        patterns.extend(extensions.iter().map(|e| Pattern::Extension(e.to_string())));
+        patterns.extend(names.iter().map(|n| Pattern::Name(n.to_string())));
+        patterns.extend(globs.iter().map(|g| Pattern::Glob(glob::Pattern::new(g).unwrap())));
 
-    fn is_image(&self) -> bool {
-        self.extension_is_one_of( &[
-            "png", "jpeg", "jpg", "gif", "bmp", "tiff", "tif",
-            "ppm", "pgm", "pbm", "pnm", "webp", "raw", "arw",
-            "svg", "stl", "eps", "dvi", "ps", "cbr",
-            "cbz", "xpm", "ico",
-        ])
+        FileCategory { name: name.to_owned(), style: None, patterns: patterns }
     }
 
-    fn is_video(&self) -> bool {
-        self.extension_is_one_of( &[
-            "avi", "flv", "m2v", "mkv", "mov", "mp4", "mpeg",
-            "mpg", "ogm", "ogv", "vob", "wmv",
-        ])
+    fn colour(&self, colours: &Colours) -> Style {
+        if let Some(style) = self.style {
+            return style;
+        }
+
+        match &*self.name {
+            "immediate"   => colours.filetypes.immediate,
+            "image"       => colours.filetypes.image,
+            "video"       => colours.filetypes.video,
+            "music"       => colours.filetypes.music,
+            "lossless"    => colours.filetypes.lossless,
+            "crypto"      => colours.filetypes.crypto,
+            "document"    => colours.filetypes.document,
+            "compressed"  => colours.filetypes.compressed,
+            "temp"        => colours.filetypes.temp,
+            "compiled"    => colours.filetypes.compiled,
+            _             => colours.filetypes.normal,
+        }
     }
 
-    fn is_music(&self) -> bool {
-        self.extension_is_one_of( &[
-            "aac", "m4a", "mp3", "ogg", "wma",
-        ])
+    fn contains(&self, file: &File) -> bool {
+        self.patterns.iter().any(|p| p.matches(file))
     }
+}
+
+
+/// One way of matching a file’s name.
+#[derive(PartialEq, Debug, Clone)]
+enum Pattern {
 
-    fn is_lossless(&self) -> bool {
-        self.extension_is_one_of( &[
-            "alac", "ape", "flac", "wav",
-        ])
+    /// The file’s extension, which has been lowercased, is this.
+    Extension(String),
+
+    /// The file’s whole name is this.
+    Name(String),
+
+    /// The file’s whole name matches this glob.
+    Glob(glob::Pattern),
+
+    /// The file was compiled from a source file that’s next to it, such as
+    /// a `.o` file next to a `.c` file. This can’t be given in a file.
+    CompiledFromSource,
+}
+
+impl Pattern {
+    fn matches(&self, file: &File) -> bool {
+        match *self {
+            Pattern::Extension(ref ext)  => file.ext.as_ref() == Some(ext),
+            Pattern::Name(ref name)      => file.name == *name,
+            Pattern::Glob(ref glob)      => glob.matches(&file.name),
+            Pattern::CompiledFromSource  => match file.dir {
+                Some(dir)  => file.get_source_files().iter().any(|path| dir.contains(path)),
+                None       => false,
+            },
+        }
     }
+}
+
 
-    fn is_crypto(&self) -> bool {
-        self.extension_is_one_of( &[
-            "zip", "tar", "Z", "gz", "bz2", "a", "ar", "7z",
-            "iso", "dmg", "tc", "rar", "par",
-        ])
+/// Everything needed to work out the category of a file.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Classifier {
+
+    /// The categories that get decided by a file’s name, in the order
+    /// they get checked in.
+    categories: Vec<FileCategory>,
+}
+
+impl Default for Classifier {
+    fn default() -> Classifier {
+        Classifier::built_in()
     }
+}
+
+impl Classifier {
+
+    /// The categories that exa has without any configuration.
+    pub fn built_in() -> Classifier {
+        let mut compiled = FileCategory::new("compiled", &[ "class", "elc", "hi", "o", "pyc" ], &[], &[]);
+        compiled.patterns.push(Pattern::CompiledFromSource);
+
+        Classifier { categories: vec![
+            FileCategory::new("immediate", &[], &[
+                "Makefile", "Cargo.toml", "SConstruct", "CMakeLists.txt",
+                "build.gradle", "Rakefile", "Gruntfile.js",
+                "Gruntfile.coffee",
+            ], &[ "README*" ]),
+
+            FileCategory::new("image", &[
+                "png", "jpeg", "jpg", "gif", "bmp", "tiff", "tif",
+                "ppm", "pgm", "pbm", "pnm", "webp", "raw", "arw",
+                "svg", "stl", "eps", "dvi", "ps", "cbr",
+                "cbz", "xpm", "ico",
+            ], &[], &[]),
+
+            FileCategory::new("video", &[
+                "avi", "flv", "m2v", "mkv", "mov", "mp4", "mpeg",
+                "mpg", "ogm", "ogv", "vob", "wmv",
+            ], &[], &[]),
+
+            FileCategory::new("music", &[
+                "aac", "m4a", "mp3", "ogg", "wma",
+            ], &[], &[]),
+
+            FileCategory::new("lossless", &[
+                "alac", "ape", "flac", "wav",
+            ], &[], &[]),
+
+            FileCategory::new("crypto", &[
+                "asc", "enc", "gpg", "pgp", "sig", "signature",
+                "pfx", "p12",
+            ], &[], &[]),
 
-    fn is_document(&self) -> bool {
-        self.extension_is_one_of( &[
-            "djvu", "doc", "docx", "dvi", "eml", "eps", "fotd",
-            "odp", "odt", "pdf", "ppt", "pptx", "rtf",
-            "xls", "xlsx",
-        ])
+            FileCategory::new("document", &[
+                "djvu", "doc", "docx", "dvi", "eml", "eps", "fotd",
+                "odp", "odt", "pdf", "ppt", "pptx", "rtf",
+                "xls", "xlsx",
+            ], &[], &[]),
+
+            FileCategory::new("compressed", &[
+                "zip", "tar", "z", "gz", "bz2", "a", "ar", "7z",
+                "iso", "dmg", "tc", "rar", "par",
+            ], &[], &[]),
+
+            FileCategory::new("temp", &[
+                "tmp", "swp", "swo", "swn", "bak",
+            ], &[], &[ "*~", "#*#" ]),
+
+            compiled,
+        ]}
     }
 
-    fn is_compressed(&self) -> bool {
-        self.extension_is_one_of( &[
-            "zip", "tar", "Z", "gz", "bz2", "a", "ar", "7z",
-            "iso", "dmg", "tc", "rar", "par"
-        ])
+    /// Load the categories in the user’s configuration directory, and then
+    /// the ones for the project in the current working directory, on top of
+    /// the built-in ones. A project file that can’t be loaded gets skipped,
+    /// with a warning.
+    pub fn load() -> Result<Classifier, String> {
+        let mut classifier = Classifier::built_in();
+
+        if let Some(directory) = config::directory() {
+            try!(classifier.add_file(&directory.join("categories.toml")));
+        }
+
+        if let Err(e) = classifier.add_file(Path::new(".exa-categories.toml")) {
+            let _ = writeln!(io::stderr(), "exa: ignoring file categories: {}", e);
+        }
+
+        Ok(classifier)
     }
 
-    fn is_temp(&self) -> bool {
-        self.name.ends_with("~")
-            || (self.name.starts_with("#") && self.name.ends_with("#"))
-            || self.extension_is_one_of( &[ "tmp", "swp", "swo", "swn", "bak" ])
+    /// Add the categories from the file at the given path, if there is one.
+    /// If any of them can’t be added, none of them are.
+    fn add_file(&mut self, path: &Path) -> Result<(), String> {
+        let contents = match try!(config::read(path)) {
+            Some(contents)  => contents,
+            None            => return Ok(()),
+        };
+
+        let mut classifier = self.clone();
+        match classifier.add(&contents) {
+            Ok(())  => { *self = classifier; Ok(()) },
+            Err(e)  => Err(format!("{}: {}", path.display(), e)),
+        }
     }
 
-    fn is_compiled(&self) -> bool {
-        if self.extension_is_one_of( &[ "class", "elc", "hi", "o", "pyc" ]) {
-            true
+    /// Add the categories from a file, where each table is a category.
+    /// Tables named after an existing category add more files to it, and
+    /// the rest become new categories, which get checked first. The
+    /// categories that files get put in by their types, rather than their
+    /// names, can’t be used.
+    pub fn add(&mut self, input: &str) -> Result<(), String> {
+        let mut new_categories = 0;
+
+        for entry in try!(config::parse(input)) {
+            if entry.table.is_empty() {
+                return Err(entry.error("not in a category"));
+            }
+
+            if [ "directory", "executable", "symlink", "special", "normal" ].contains(&&*entry.table) {
+                return Err(entry.error(&format!("{} can’t be given files by name", entry.table)));
+            }
+
+            let index = match self.categories.iter().position(|c| c.name == entry.table) {
+                Some(index) => index,
+                None => {
+                    self.categories.insert(new_categories, FileCategory::new(&entry.table, &[], &[], &[]));
+                    new_categories += 1;
+                    new_categories - 1
+                },
+            };
+
+            let category = &mut self.categories[index];
+            let values = match entry.value {
+                Value::String(ref s)   => vec![ s.clone() ],
+                Value::List(ref list)  => list.clone(),
+            };
+
+            match &*entry.key {
+                "colour" | "color" => match (values.len(), values.first().and_then(|s| parse_style(s))) {
+                    (1, Some(style))  => category.style = Some(style),
+                    _                 => return Err(entry.error("invalid style")),
+                },
+                "extensions" => {
+                    category.patterns.extend(values.iter().map(|e| Pattern::Extension(e.to_ascii_lowercase())));
+                },
+                "names" => {
+                    category.patterns.extend(values.into_iter().map(Pattern::Name));
+                },
+                "globs" => for glob in values {
+                    match glob::Pattern::new(&glob) {
+                        Ok(pattern)  => category.patterns.push(Pattern::Glob(pattern)),
+                        Err(e)       => return Err(entry.error(&format!("invalid glob {}: {}", glob, e.msg))),
+                    }
+                },
+                _ => return Err(entry.error(&format!("unknown key {}", entry.key))),
+            }
         }
-        else if let Some(dir) = self.dir {
-            self.get_source_files().iter().any(|path| dir.contains(path))
+
+        Ok(())
+    }
+
+    /// Forget the styles given to the categories, for when the output
+    /// isn’t colourful but the categories are still needed.
+    pub fn remove_styles(&mut self) {
+        for category in &mut self.categories {
+            category.style = None;
         }
+    }
+
+    /// Every category, in the order they get checked in.
+    pub fn categories(&self) -> Vec<Category> {
+        let mut categories = vec![ Category::Directory, Category::Executable, Category::Symlink, Category::Special ];
+        categories.extend(self.categories.iter().map(Category::Named));
+        categories.push(Category::Normal);
+        categories
+    }
+
+    /// The category the given file belongs in.
+    pub fn category(&self, file: &File) -> Category {
+        if file.is_directory()             { Category::Directory }
+        else if file.is_executable_file()  { Category::Executable }
+        else if file.is_link()             { Category::Symlink }
+        else if !file.is_file()            { Category::Special }
         else {
-            false
+            // A file whose contents have been sniffed goes by its format,
+            // rather than by its name.
+            if let Some(name) = file.magic.and_then(|m| m.category()) {
+                if let Some(category) = self.categories.iter().find(|c| c.name == name) {
+                    return Category::Named(category);
+                }
+            }
+
+            match self.categories.iter().find(|c| c.contains(file)) {
+                Some(category)  => Category::Named(category),
+                None            => Category::Normal,
+            }
         }
     }
 }
 
 
+/// The name of the colour that a file’s name gets painted with, such as
+/// `filetypes-image`. This is always the colour of its category, even when
+/// the style itself comes from a theme or `LS_COLORS`.
+pub fn file_class(colours: &Colours, file: &File) -> String {
+    format!("filetypes-{}", colours.classifier.category(file).name())
+}
+
+pub fn file_colour(colours: &Colours, file: &File) -> Style {
+    if let Some(style) = colours.names.style(file) {
+        return style;
+    }
+
+    let category = colours.classifier.category(file);
+    if !colours.own_categories.contains(&category.name()) {
+        if let Some(style) = colours.ls_colors.style(file) {
+            return style;
+        }
+    }
+
+    category.colour(colours)
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Classifier, Pattern};
+    use ansi_term::Colour::Yellow;
+    use std::path::Path;
+    use file::File;
+    use fixture::Fixture;
+    use magic::Magic;
+
+    #[test]
+    fn new_category() {
+        let mut classifier = Classifier::built_in();
+        classifier.add("[source]\ncolour = \"yellow\"\nextensions = [ \"RS\", \"c\" ]\n").unwrap();
+
+        let names: Vec<&str> = classifier.categories().iter().map(|c| c.name()).collect();
+        assert_eq!(&names[.. 6], &[ "directory", "executable", "symlink", "special", "source", "immediate" ]);
+        assert_eq!(classifier.categories[0].style, Some(Yellow.normal()));
+        assert_eq!(classifier.categories[0].patterns, vec![ Pattern::Extension("rs".to_owned()), Pattern::Extension("c".to_owned()) ]);
+    }
+
+    #[test]
+    fn existing_category() {
+        let mut classifier = Classifier::built_in();
+        classifier.add("[immediate]\nnames = \"justfile\"\n").unwrap();
+
+        assert_eq!(classifier.categories[0].name, "immediate");
+        assert_eq!(classifier.categories[0].patterns.last(), Some(&Pattern::Name("justfile".to_owned())));
+    }
+
+    #[test]
+    fn unknown_key() {
+        let mut classifier = Classifier::built_in();
+        assert_eq!(classifier.add("[source]\nsuffixes = \".rs\"\n").unwrap_err(), "line 2: unknown key suffixes")
+    }
+
+    #[test]
+    fn fixed_category() {
+        let mut classifier = Classifier::built_in();
+        assert_eq!(classifier.add("[directory]\nnames = \"src\"\n").unwrap_err(), "line 2: directory can’t be given files by name")
+    }
+
+    #[test]
+    fn bad_glob() {
+        let mut classifier = Classifier::built_in();
+        assert!(classifier.add("[source]\nglobs = [ \"[*\" ]\n").is_err())
+    }
+
+    #[test]
+    fn sniffed_format() {
+        let mut file = File::from_path(Path::new("Cargo.toml"), None).unwrap();
+        file.magic = Some(Magic::Png);
+        assert_eq!(Classifier::built_in().category(&file).name(), "image")
+    }
+
+    #[test]
+    fn sniffed_script_without_exec_bit() {
+        let mut file = File::from_path(Path::new("Cargo.toml"), None).unwrap();
+        file.magic = Some(Magic::Script);
+        assert_eq!(Classifier::built_in().category(&file).name(), "immediate")
+    }
+
+    #[test]
+    fn bad_file_adds_nothing() {
+        let fixture = Fixture::new("bad-categories");
+        let path = fixture.file("categories.toml", b"[source]\nextensions = [ \"rs\" ]\n\n[broken]\nsuffixes = \".x\"\n");
+
+        let mut classifier = Classifier::built_in();
+        let result = classifier.add_file(&path);
+
+        assert!(result.is_err());
+        assert_eq!(classifier, Classifier::built_in());
+    }
+}
+
+
 #[cfg(broken_test)]
 mod test {
     use super::*;
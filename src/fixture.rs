@@ -0,0 +1,57 @@
+//! **Fixtures** for the tests that need real files to look at.
+//!
+//! Each fixture gets its own directory under the system’s temporary
+//! directory, named after both the test and the process running it, so
+//! tests running at the same time never share one. The directory is removed
+//! when the fixture is dropped, which happens even if the test panics.
+
+use std::env::temp_dir;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process;
+
+
+/// A temporary directory of files for a test to use.
+pub struct Fixture {
+    path: PathBuf,
+}
+
+impl Fixture {
+
+    /// Create a new, empty fixture directory with the given name.
+    ///
+    /// The path is canonicalised, so it matches the paths that Git reports
+    /// even when the temporary directory is behind a symlink.
+    pub fn new(name: &str) -> Fixture {
+        let path = temp_dir().join(format!("exa-test-{}-{}", name, process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Fixture { path: fs::canonicalize(&path).unwrap() }
+    }
+
+    /// The path to the fixture directory itself.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Create a directory, and any of its parents, inside the fixture.
+    pub fn dir(&self, name: &str) -> PathBuf {
+        let path = self.path.join(name);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    /// Create a file with the given contents inside the fixture.
+    pub fn file(&self, name: &str, contents: &[u8]) -> PathBuf {
+        let path = self.path.join(name);
+        fs::File::create(&path).unwrap().write_all(contents).unwrap();
+        path
+    }
+}
+
+impl Drop for Fixture {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
@@ -0,0 +1,247 @@
+//! Parsing the `LS_COLORS` environment variable, which is how `ls` (along
+//! with `tree`, `fd`, and others) gets told which colours to use for which
+//! files. It’s usually set by running `dircolors`.
+//!
+//! The variable is a colon-separated list of `key=value` entries, where
+//! each value is a list of ANSI SGR codes, such as `01;34` for bold blue.
+//! A key is either a two-letter code for a type of file, such as `di` for
+//! directories, or a `*` followed by the end of a file name, such as
+//! `*.tar`. Entries that can’t be understood get ignored, as there are
+//! plenty of codes that exa doesn’t have any use for.
+
+use std::os::unix::fs::{FileTypeExt, PermissionsExt};
+
+use ansi_term::Style;
+use ansi_term::Colour::{self, Black, Red, Green, Yellow, Blue, Purple, Cyan, White, Fixed};
+
+use file::File;
+
+
+/// The styles from `LS_COLORS` that override exa’s own file name colours.
+/// Any type of file that doesn’t have a style here keeps its usual colour.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct LsColors {
+    directory:              Option<Style>,
+    symlink:                Option<Style>,
+    executable:             Option<Style>,
+    pipe:                   Option<Style>,
+    socket:                 Option<Style>,
+    block_device:           Option<Style>,
+    char_device:            Option<Style>,
+    orphan:                 Option<Style>,
+    setuid:                 Option<Style>,
+    setgid:                 Option<Style>,
+    sticky_other_writable:  Option<Style>,
+    other_writable:         Option<Style>,
+
+    /// Styles for files whose names end with the given text, such as
+    /// `.tar`, in the order they were given in.
+    suffixes: Vec<(String, Style)>,
+}
+
+impl LsColors {
+
+    /// Parse the value of an `LS_COLORS` variable.
+    pub fn parse(input: &str) -> LsColors {
+        let mut colors = LsColors::default();
+
+        for entry in input.split(':') {
+            let mut parts = entry.splitn(2, '=');
+            let (key, value) = match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) => (key, value),
+                _                        => continue,
+            };
+
+            let style = match parse_style(value) {
+                Some(style)  => style,
+                None         => continue,
+            };
+
+            if key.starts_with('*') {
+                colors.suffixes.push((key[1..].to_owned(), style));
+                continue;
+            }
+
+            let field = match key {
+                "di"  => &mut colors.directory,
+                "ln"  => &mut colors.symlink,
+                "ex"  => &mut colors.executable,
+                "pi"  => &mut colors.pipe,
+                "so"  => &mut colors.socket,
+                "bd"  => &mut colors.block_device,
+                "cd"  => &mut colors.char_device,
+                "or"  => &mut colors.orphan,
+                "su"  => &mut colors.setuid,
+                "sg"  => &mut colors.setgid,
+                "tw"  => &mut colors.sticky_other_writable,
+                "ow"  => &mut colors.other_writable,
+                _     => continue,
+            };
+
+            *field = Some(style);
+        }
+
+        colors
+    }
+
+    /// The style to paint the given file’s name with, if there’s one that
+    /// applies to it. Just like with `ls`, the type of a file takes priority,
+    /// and its name only gets looked at if it’s a regular file that isn’t
+    /// executable or setuid.
+    pub fn style(&self, file: &File) -> Option<Style> {
+        let file_type = file.metadata.file_type();
+        let mode = file.metadata.permissions().mode();
+
+        if file.is_directory() {
+            let other_writable = mode & 0o002 != 0;
+            let sticky = mode & 0o1000 != 0;
+
+            if other_writable && sticky && self.sticky_other_writable.is_some() { self.sticky_other_writable }
+            else if other_writable && self.other_writable.is_some()            { self.other_writable }
+            else                                                                { self.directory }
+        }
+        else if file.is_link() {
+            if self.orphan.is_some() && file.link_target().is_err() { self.orphan }
+                                                                 else { self.symlink }
+        }
+        else if file.is_pipe()                { self.pipe }
+        else if file.is_socket()              { self.socket }
+        else if file_type.is_block_device()   { self.block_device }
+        else if file_type.is_char_device()    { self.char_device }
+        else if mode & 0o4000 != 0 && self.setuid.is_some()  { self.setuid }
+        else if mode & 0o2000 != 0 && self.setgid.is_some()  { self.setgid }
+        else if file.is_executable_file()     { self.executable }
+        else {
+            // Later entries override earlier ones, so the last suffix that
+            // matches wins.
+            self.suffixes.iter().rev()
+                .find(|&&(ref suffix, _)| file.name.ends_with(&**suffix))
+                .map(|&(_, style)| style)
+        }
+    }
+}
+
+/// Parse a list of ANSI SGR codes, such as `01;38;5;208`, into a style.
+/// Returns `None` if any of the codes aren’t numbers.
+pub fn parse_style(input: &str) -> Option<Style> {
+    let mut codes = Vec::new();
+    for code in input.split(';') {
+        match code.parse::<u8>() {
+            Ok(number)  => codes.push(number),
+            Err(_)      => return None,
+        }
+    }
+
+    let mut foreground = None;
+    let mut background = None;
+    let (mut bold, mut dimmed, mut italic, mut underline) = (false, false, false, false);
+    let (mut blink, mut reverse, mut hidden) = (false, false, false);
+
+    let mut iter = codes.into_iter();
+    while let Some(code) = iter.next() {
+        match code {
+            0         => { foreground = None; background = None; bold = false; dimmed = false;
+                           italic = false; underline = false; blink = false; reverse = false; hidden = false; },
+            1         => bold = true,
+            2         => dimmed = true,
+            3         => italic = true,
+            4         => underline = true,
+            5         => blink = true,
+            7         => reverse = true,
+            8         => hidden = true,
+            30 ... 37 => foreground = Some(basic_colour(code - 30)),
+            38        => foreground = extended_colour(&mut iter),
+            39        => foreground = None,
+            40 ... 47 => background = Some(basic_colour(code - 40)),
+            48        => background = extended_colour(&mut iter),
+            49        => background = None,
+            90 ... 97 => foreground = Some(Fixed(code - 90 + 8)),
+            100 ... 107 => background = Some(Fixed(code - 100 + 8)),
+            _         => {},
+        }
+    }
+
+    let mut style = match foreground {
+        Some(colour)  => colour.normal(),
+        None          => Style::default(),
+    };
+
+    if bold       { style = style.bold() }
+    if dimmed     { style = style.dimmed() }
+    if italic     { style = style.italic() }
+    if underline  { style = style.underline() }
+    if blink      { style = style.blink() }
+    if reverse    { style = style.reverse() }
+    if hidden     { style = style.hidden() }
+
+    if let Some(colour) = background {
+        style = style.on(colour);
+    }
+
+    Some(style)
+}
+
+fn basic_colour(number: u8) -> Colour {
+    match number {
+        0 => Black,
+        1 => Red,
+        2 => Green,
+        3 => Yellow,
+        4 => Blue,
+        5 => Purple,
+        6 => Cyan,
+        _ => White,
+    }
+}
+
+/// Read the rest of a `38` or `48` code, which should be `5` followed by
+/// the number of one of the 256 colours. The 24-bit form can’t be
+/// displayed, so its three components get skipped over.
+fn extended_colour<I: Iterator<Item=u8>>(iter: &mut I) -> Option<Colour> {
+    match iter.next() {
+        Some(5) => iter.next().map(Fixed),
+        Some(2) => { let _ = iter.next(); let _ = iter.next(); let _ = iter.next(); None },
+        _       => None,
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{parse_style, LsColors};
+    use ansi_term::Style;
+    use ansi_term::Colour::{Black, Blue, Red, Yellow, Fixed};
+
+    #[test]
+    fn bold_blue() {
+        assert_eq!(parse_style("01;34"), Some(Blue.bold()))
+    }
+
+    #[test]
+    fn background() {
+        assert_eq!(parse_style("30;41"), Some(Black.on(Red)))
+    }
+
+    #[test]
+    fn fixed() {
+        assert_eq!(parse_style("38;5;208;4"), Some(Fixed(208).underline()))
+    }
+
+    #[test]
+    fn reset() {
+        assert_eq!(parse_style("0"), Some(Style::default()))
+    }
+
+    #[test]
+    fn not_a_style() {
+        assert_eq!(parse_style("bold"), None)
+    }
+
+    #[test]
+    fn entries() {
+        let colors = LsColors::parse("rs=0:di=01;34:ln=target:*.tar=01;31:*.jpg=33:");
+        assert_eq!(colors.directory, Some(Blue.bold()));
+        assert_eq!(colors.symlink, None);
+        assert_eq!(colors.suffixes, vec![ (".tar".to_owned(), Red.bold()), (".jpg".to_owned(), Yellow.normal()) ]);
+    }
+}
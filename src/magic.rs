@@ -0,0 +1,98 @@
+//! Detecting the format of a file from its **magic number**: the first few
+//! bytes of its contents, which many formats start with so they can be
+//! recognised no matter what the file is called.
+//!
+//! This only gets done when the user asks for it with `--sniff`, as it
+//! means opening and reading from every file in the listing.
+
+
+/// The number of bytes to read from the start of a file. This is more than
+/// any of the formats here need, so no file gets read past this point.
+pub const SNIFF_LENGTH: u64 = 512;
+
+
+/// A format that a file’s contents can be in.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum Magic {
+    Elf,
+    Png,
+    Jpeg,
+    Gzip,
+    Zip,
+    Pdf,
+
+    /// A script, starting with a `#!` line that says which interpreter to
+    /// run it with.
+    Script,
+}
+
+impl Magic {
+
+    /// Work out the format of a file from the bytes at the start of it,
+    /// if it’s one of the formats that can be detected.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Magic> {
+        if bytes.starts_with(b"\x7FELF")                       { Some(Magic::Elf) }
+        else if bytes.starts_with(b"\x89PNG\r\n\x1A\n")        { Some(Magic::Png) }
+        else if bytes.starts_with(b"\xFF\xD8\xFF")             { Some(Magic::Jpeg) }
+        else if bytes.starts_with(b"\x1F\x8B")                 { Some(Magic::Gzip) }
+        else if bytes.starts_with(b"PK\x03\x04")
+             || bytes.starts_with(b"PK\x05\x06")               { Some(Magic::Zip) }
+        else if bytes.starts_with(b"%PDF-")                    { Some(Magic::Pdf) }
+        else if bytes.starts_with(b"#!")                       { Some(Magic::Script) }
+        else                                                   { None }
+    }
+
+    /// The name of this format, as it gets shown in the Type column.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            Magic::Elf     => "ELF",
+            Magic::Png     => "PNG",
+            Magic::Jpeg    => "JPEG",
+            Magic::Gzip    => "gzip",
+            Magic::Zip     => "zip",
+            Magic::Pdf     => "PDF",
+            Magic::Script  => "script",
+        }
+    }
+
+    /// The name of the category that files in this format belong to,
+    /// which decides their colour. Scripts don’t have one: like any other
+    /// file, they only get coloured as executables if they can be run.
+    pub fn category(&self) -> Option<&'static str> {
+        match *self {
+            Magic::Elf     => Some("compiled"),
+            Magic::Png     => Some("image"),
+            Magic::Jpeg    => Some("image"),
+            Magic::Gzip    => Some("compressed"),
+            Magic::Zip     => Some("compressed"),
+            Magic::Pdf     => Some("document"),
+            Magic::Script  => None,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::Magic;
+
+    #[test]
+    fn png() {
+        assert_eq!(Magic::from_bytes(b"\x89PNG\r\n\x1A\n\0\0\0\rIHDR"), Some(Magic::Png))
+    }
+
+    #[test]
+    fn script() {
+        assert_eq!(Magic::from_bytes(b"#!/bin/sh\necho hi\n"), Some(Magic::Script))
+    }
+
+    #[test]
+    fn too_short() {
+        assert_eq!(Magic::from_bytes(b"\x7FEL"), None)
+    }
+
+    #[test]
+    fn text() {
+        assert_eq!(Magic::from_bytes(b"Hello, world!\n"), None)
+    }
+}
@@ -5,11 +5,13 @@
 extern crate ansi_term;
 extern crate datetime;
 extern crate getopts;
+extern crate glob;
 extern crate libc;
 extern crate locale;
 extern crate natord;
 extern crate num_cpus;
 extern crate number_prefix;
+extern crate rustc_serialize;
 extern crate scoped_threadpool;
 extern crate term_grid;
 extern crate unicode_width;
@@ -19,25 +21,38 @@ extern crate users;
 #[macro_use] extern crate lazy_static;
 
 use std::env;
+use std::io::{self, Write};
 use std::path::{Component, Path};
 use std::process;
 
 use dir::Dir;
 use file::File;
 use options::{Options, View};
+use output::Summary;
+use output::column::SizeFormat;
+use output::html::{self, escape};
 
+mod collate;
 mod colours;
+mod config;
 mod dir;
 mod feature;
 mod file;
 mod filetype;
+#[cfg(test)] mod fixture;
+mod ls_colors;
+mod magic;
 mod options;
 mod output;
 mod term;
+mod theme;
 
 
 struct Exa {
     options: Options,
+
+    /// The user’s locale for numbers, which the summary lines use.
+    numeric: locale::Numeric,
 }
 
 impl Exa {
@@ -49,16 +64,20 @@ impl Exa {
             args_file_names.push(".".to_owned());
         }
 
+        if self.options.is_html() {
+            println!("<pre class=\"exa\">");
+        }
+
         for file_name in args_file_names.iter() {
             match File::from_path(Path::new(&file_name), None) {
                 Err(e) => {
-                    println!("{}: {}", file_name, e);
+                    self.print_error(format!("{}: {}", file_name, e));
                 },
                 Ok(f) => {
                     if f.is_directory() && !self.options.dir_action.treat_dirs_as_files() {
                         match f.to_dir(self.options.should_scan_for_git()) {
                             Ok(d) => dirs.push(d),
-                            Err(e) => println!("{}: {}", file_name, e),
+                            Err(e) => self.print_error(format!("{}: {}", file_name, e)),
                         }
                     }
                     else {
@@ -68,75 +87,162 @@ impl Exa {
             }
         }
 
+        // In tree mode, the directories given on the command line get
+        // listed along with everything inside them, so the totals of the
+        // whole tree get printed after it.
+        let tree = self.options.dir_action.recurse_options().map_or(false, |r| r.tree);
+
         let no_files = files.is_empty();
         if !no_files {
-            self.print_files(None, files);
+            let summary = self.print_files(None, files, true);
+            if self.options.summary && tree {
+                self.print_summary("Total", &summary);
+            }
         }
 
         let is_only_dir = dirs.len() == 1;
-        self.print_dirs(dirs, no_files, is_only_dir);
+        let mut grand_total = Summary::default();
+        self.print_dirs(dirs, no_files, is_only_dir, &mut grand_total);
+
+        // Recursing prints a summary after every directory, so the totals
+        // of all of them get printed at the end.
+        let recursing = self.options.dir_action.recurse_options().map_or(false, |r| !r.tree);
+        if self.options.summary && recursing {
+            print!("\n");
+            self.print_summary("Grand total", &grand_total);
+        }
+
+        if self.options.is_html() {
+            println!("</pre>");
+        }
     }
 
-    fn print_dirs(&self, dir_files: Vec<Dir>, mut first: bool, is_only_dir: bool) {
+    fn print_dirs(&self, dir_files: Vec<Dir>, mut first: bool, is_only_dir: bool, grand_total: &mut Summary) {
+        let headers = self.options.prints_headers();
+
         for dir in dir_files {
+            let first_listing = first;
 
             // Put a gap between directories, or between the list of files and the
             // first directory.
             if first {
                 first = false;
             }
-            else {
+            else if headers {
                 print!("\n");
             }
 
-            if !is_only_dir {
-                println!("{}:", dir.path.display());
+            if !is_only_dir && headers {
+                self.print_line(format!("{}:", dir.path.display()));
             }
 
             let mut children = Vec::new();
-            for file in dir.files() {
+            for file in dir.files(self.options.filter.dot_filter) {
                 match file {
                     Ok(file)       => children.push(file),
-                    Err((path, e)) => println!("[{}: {}]", path.display(), e),
+                    Err((path, e)) => self.print_error(format!("[{}: {}]", path.display(), e)),
                 }
             };
 
             self.options.filter.filter_files(&mut children);
             self.options.filter.sort_files(&mut children);
 
+            // Find the directories to recurse into *before* removing the
+            // files that shouldn't be displayed, as a directory that gets
+            // hidden from the listing may still have files worth showing.
+            let mut child_dirs = Vec::new();
             if let Some(recurse_opts) = self.options.dir_action.recurse_options() {
                 let depth = dir.path.components().filter(|&c| c != Component::CurDir).count() + 1;
                 if !recurse_opts.tree && !recurse_opts.is_too_deep(depth) {
-
-                    let mut child_dirs = Vec::new();
-                    for child_dir in children.iter().filter(|f| f.is_directory()) {
-                        match child_dir.to_dir(false) {
+                    for child_dir in children.iter().filter(|f| f.is_directory() && !f.is_all_all) {
+                        match child_dir.to_dir(self.options.should_scan_for_git()) {
                             Ok(d)  => child_dirs.push(d),
-                            Err(e) => println!("{}: {}", child_dir.path.display(), e),
+                            Err(e) => self.print_error(format!("{}: {}", child_dir.path.display(), e)),
                         }
                     }
+                }
+            }
 
-                    self.print_files(Some(&dir), children);
+            // In tree mode, the view hides the files that don’t match the
+            // filter by itself, as it has to look inside the directories that
+            // don’t match to find any files that do.
+            let tree = self.options.dir_action.recurse_options().map_or(false, |r| r.tree);
+            if !tree {
+                children.retain(|f| self.options.filter.matches(f));
+            }
 
-                    if !child_dirs.is_empty() {
-                        self.print_dirs(child_dirs, false, false);
-                    }
+            let summary = self.print_files(Some(&dir), children, first_listing);
 
-                    continue;
-                }
+            if self.options.summary {
+                self.print_summary("Total", &summary);
+                *grand_total = *grand_total + summary;
             }
 
-            self.print_files(Some(&dir), children);
+            if !child_dirs.is_empty() {
+                self.print_dirs(child_dirs, false, false, grand_total);
+            }
+        }
+    }
+
+    /// Print the given files with the view, returning the totals of the
+    /// files that were shown. Only the first listing gets a header row when
+    /// printing delimited records, so they can all be read as one table.
+    fn print_files(&self, dir: Option<&Dir>, mut files: Vec<File>, first_listing: bool) -> Summary {
+        let summary = Summary::of(&files);
+        self.options.filter.sniff_files(&mut files);
+
+        match self.options.view {
+            View::Grid(ref g)         => g.view(&files),
+            View::Details(ref d)      => return d.view(dir, files, first_listing),
+            View::GridDetails(ref gd) => gd.view(dir, files),
+            View::Lines(ref l)        => l.view(&files),
+            View::Json(ref j)         => j.view(files),
+            View::Print0(ref p)       => p.view(&files),
+            View::Count(ref c)        => c.view(dir, &files),
+        }
+
+        summary
+    }
+
+    /// Print a line of totals, in the same colours as the view, and with the
+    /// size in the same format as the details view if it’s being used.
+    fn print_summary(&self, label: &str, summary: &Summary) {
+        let (size_format, colours) = match self.options.view {
+            View::Details(ref d)       => (d.columns.map_or(SizeFormat::default(), |c| c.size_format), &d.colours),
+            View::GridDetails(ref gd)  => (gd.details.columns.map_or(SizeFormat::default(), |c| c.size_format), &gd.details.colours),
+            View::Grid(ref g)          => (SizeFormat::default(), &g.colours),
+            View::Lines(ref l)         => (SizeFormat::default(), &l.colours),
+            View::Json(_) | View::Print0(_) | View::Count(_) => return,
+        };
+
+        let cell = summary.render(label, size_format, &self.numeric, colours);
+
+        if self.options.is_html() {
+            println!("{}", html::render(&cell));
+        }
+        else {
+            println!("{}", cell);
+        }
+    }
 
+    /// Print a line that isn’t part of any view, such as a directory’s name
+    /// or an error, escaping it first if the view is printing HTML.
+    fn print_line(&self, line: String) {
+        if self.options.is_html() {
+            println!("{}", escape(&line));
+        }
+        else {
+            println!("{}", line);
         }
     }
 
-    fn print_files(&self, dir: Option<&Dir>, files: Vec<File>) {
+    /// Print an error about a file or directory that couldn’t be read. The
+    /// JSON and NUL-separated views write these to standard error instead,
+    /// so they can’t break the output or get read back in as paths.
+    fn print_error(&self, line: String) {
         match self.options.view {
-            View::Grid(g)         => g.view(&files),
-            View::Details(d)      => d.view(dir, files),
-            View::GridDetails(gd) => gd.view(dir, &files),
-            View::Lines(l)        => l.view(&files),
+            View::Json(_) | View::Print0(_) => { let _ = writeln!(io::stderr(), "{}", line); },
+            _                               => self.print_line(line),
         }
     }
 }
@@ -147,7 +253,8 @@ fn main() {
 
     match Options::getopts(&args) {
         Ok((options, paths)) => {
-            let mut exa = Exa { options: options };
+            let numeric = locale::Numeric::load_user_locale().unwrap_or_else(|_| locale::Numeric::english());
+            let mut exa = Exa { options: options, numeric: numeric };
             exa.run(paths);
         },
         Err(e) => {
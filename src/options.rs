@@ -1,23 +1,35 @@
 use std::cmp;
 use std::env::var_os;
 use std::fmt;
+use std::fs;
 use std::num::ParseIntError;
 use std::os::unix::fs::MetadataExt;
 
+use datetime::local::{LocalDate, LocalDateTime, LocalTime};
+use datetime::instant::Instant;
 use getopts;
+use glob;
 use natord;
+use num_cpus;
+use scoped_threadpool::Pool;
 
+use collate;
 use colours::Colours;
+use dir::DotFilter;
 use feature::xattr;
 use file::File;
-use output::{Grid, Details, GridDetails, Lines};
-use output::column::{Columns, TimeTypes, SizeFormat};
+use filetype::Classifier;
+use ls_colors::LsColors;
+use output::{sniff_files, Count, Grid, Details, GridDetails, Json, Lines, PathFormat, Print0};
+use output::details::{TableFormat, TotalSize, TreeStyle};
+use output::column::{Columns, TimeType, TimeTypes, SizeFormat};
 use term::dimensions;
+use theme::Theme;
 
 
 /// These **options** represent a parsed, error-checked versions of the
 /// user's command-line options.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct Options {
 
     /// The action to perform when encountering a directory rather than a
@@ -29,6 +41,9 @@ pub struct Options {
 
     /// The type of output to use (lines, grid, or details).
     pub view: View,
+
+    /// Whether to print a line of totals after each directory's listing.
+    pub summary: bool,
 }
 
 impl Options {
@@ -48,15 +63,34 @@ impl Options {
         opts.optflag("R", "recurse",   "recurse into directories");
         opts.optflag("T", "tree",      "recurse into subdirectories in a tree view");
         opts.optflag("x", "across",    "sort multi-column view entries across");
+        opts.optopt ("",  "tree-style", "which characters to draw the tree with", "STYLE");
+        opts.optflag("",  "json",      "display details of each file as JSON");
+        opts.optflag("0", "print0",    "display bare paths separated by NUL bytes");
+        opts.optflag("",  "html",      "display the listing as HTML, with CSS classes for colours");
+        opts.optflagopt("", "count",   "display how many files of each kind there are", "WHAT");
         opts.optopt ("",  "color",     "when to show anything in colours", "WHEN");
         opts.optopt ("",  "colour",    "when to show anything in colours (alternate spelling)", "WHEN");
+        opts.optopt ("",  "theme",     "which theme from the configuration directory to use", "NAME");
+        opts.optflag("",  "absolute",  "display the full path of each file");
+        opts.optopt ("",  "relative-to", "display each file's path relative to a directory", "DIR");
+        opts.optflag("",  "summary",   "display the totals of each directory after its listing");
 
         // Filtering and sorting options
         opts.optflag("",  "group-directories-first", "list directories before other files");
-        opts.optflag("a", "all",       "show dot-files");
+        opts.optopt ("",  "group-dotfiles", "where to list dotfiles: first, last, or mixed", "WHERE");
+        opts.optflagmulti("a", "all",  "show dot-files (twice to also show . and ..)");
         opts.optflag("d", "list-dirs", "list directories as regular files");
+        opts.optmulti("I", "ignore-glob", "ignore files that match these glob patterns", "GLOBS");
         opts.optflag("r", "reverse",   "reverse order of files");
-        opts.optopt ("s", "sort",      "field to sort by", "WORD");
+        opts.optflag("",  "only-dirs", "list only directories");
+        opts.optflag("",  "only-files", "list only regular files");
+        opts.optflag("",  "only-links", "list only symbolic links");
+        opts.optopt ("",  "only",      "list only files of these kinds", "KINDS");
+        opts.optopt ("",  "min-size",  "list only files at least this big", "SIZE");
+        opts.optopt ("",  "max-size",  "list only files at most this big", "SIZE");
+        opts.optopt ("",  "newer",     "list only files changed after this time", "TIME");
+        opts.optopt ("",  "older",     "list only files changed before this time", "TIME");
+        opts.optopt ("s", "sort",      "fields to sort by", "WORDS");
 
         // Long view options
         opts.optflag("b", "binary",    "use binary prefixes in file sizes");
@@ -68,12 +102,17 @@ impl Options {
         opts.optopt ("L", "level",     "maximum depth of recursion", "DEPTH");
         opts.optflag("m", "modified",  "display timestamp of most recent modification");
         opts.optflag("S", "blocks",    "show number of file system blocks");
+        opts.optflagopt("", "total-size", "show the total size of directories' contents", "HOW");
+        opts.optflag("",  "sniff",     "show the type of each file, read from its contents");
+        opts.optopt ("",  "format",    "print the table as delimited records", "WORD");
+        opts.optflag("",  "porcelain", "use a long view format that's stable for scripts");
         opts.optopt ("t", "time",      "which timestamp to show for a file", "WORD");
         opts.optflag("u", "accessed",  "display timestamp of last access for a file");
         opts.optflag("U", "created",   "display timestamp of creation for a file");
 
         if cfg!(feature="git") {
             opts.optflag("", "git", "show git status");
+            opts.optflag("", "git-ignore", "ignore files mentioned in '.gitignore'");
         }
 
         if xattr::ENABLED {
@@ -90,6 +129,11 @@ impl Options {
 
             if !matches.opt_present("long") {
                 help_string.push_str(OPTIONS);
+
+                if cfg!(feature="git") {
+                    help_string.push_str(GIT_FILTER_HELP);
+                    help_string.push('\n');
+                }
             }
 
             help_string.push_str(LONG_OPTIONS);
@@ -118,41 +162,90 @@ impl Options {
     /// status column. It's only worth trying to discover a repository if the
     /// results will end up being displayed.
     pub fn should_scan_for_git(&self) -> bool {
+        if self.filter.should_scan_for_git() {
+            return true;
+        }
+
         match self.view {
             View::Details(Details { columns: Some(cols), .. }) => cols.should_scan_for_git(),
             View::GridDetails(GridDetails { details: Details { columns: Some(cols), .. }, .. }) => cols.should_scan_for_git(),
+            View::Json(_) => true,
             _ => false,
         }
     }
+
+    /// Whether the View specified in this set of options prints HTML, which
+    /// has to be surrounded by a `pre` element.
+    pub fn is_html(&self) -> bool {
+        match self.view {
+            View::Details(ref d) => d.html,
+            View::Lines(ref l)   => l.html,
+            _                    => false,
+        }
+    }
+
+    /// Whether a directory’s name should be printed before its files, with
+    /// a blank line between directories. Views whose output gets read back
+    /// in by other programs leave these out: each JSON object or
+    /// NUL-separated path already says where its file is, and anything
+    /// else would break a file of delimited records.
+    pub fn prints_headers(&self) -> bool {
+        match self.view {
+            View::Json(_) | View::Print0(_)  => false,
+            View::Details(ref d)             => d.format == TableFormat::Padded,
+            View::Count(ref c)               => !c.json,
+            _                                => true,
+        }
+    }
 }
 
 impl OptionSet for Options {
     fn deduce(matches: &getopts::Matches) -> Result<Options, Misfire> {
         let dir_action = try!(DirAction::deduce(&matches));
         let filter = try!(FileFilter::deduce(&matches));
-        let view = try!(View::deduce(&matches, filter, dir_action));
+        let view = try!(View::deduce(&matches, filter.clone(), dir_action));
 
         Ok(Options {
             dir_action: dir_action,
             view:       view,
             filter:     filter,
+            summary:    matches.opt_present("summary"),
         })
     }
 }
 
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub enum View {
+    Count(Count),
     Details(Details),
     Grid(Grid),
     GridDetails(GridDetails),
+    Json(Json),
     Lines(Lines),
+    Print0(Print0),
 }
 
 impl View {
     fn deduce(matches: &getopts::Matches, filter: FileFilter, dir_action: DirAction) -> Result<View, Misfire> {
         use self::Misfire::*;
 
+        let path_format = try!(PathFormat::deduce(matches));
+        let tree_style = try!(TreeStyle::deduce(matches));
+
+        // The theme gets loaded once, here, for whichever view uses it. It
+        // gets checked even when the output won’t be colourful, so a mistake
+        // in it doesn’t go unnoticed until it is.
+        let theme = try!(theme(matches));
+        if let Some(ref theme) = theme {
+            try!(Colours::colourful().set_theme(theme.clone()).map_err(FailedTheme));
+        }
+
+        // Every view would ignore the tree style without a tree to draw.
+        if matches.opt_present("tree-style") && !matches.opt_present("tree") {
+            return Err(Useless("tree-style", false, "tree"));
+        }
+
         let long = || {
             if matches.opt_present("across") && !matches.opt_present("grid") {
                 Err(Useless("across", true, "long"))
@@ -160,14 +253,47 @@ impl View {
             else if matches.opt_present("oneline") {
                 Err(Useless("oneline", true, "long"))
             }
+            else if matches.opt_present("format") && matches.opt_present("grid") {
+                Err(Conflict("format", "grid"))
+            }
+            else if matches.opt_present("porcelain") && matches.opt_present("grid") {
+                Err(Conflict("porcelain", "grid"))
+            }
+            else if matches.opt_present("porcelain") && matches.opt_present("binary") {
+                Err(Conflict("porcelain", "binary"))
+            }
+            else if matches.opt_present("summary") && matches.opt_present("format") {
+                Err(Conflict("summary", "format"))
+            }
+            else if matches.opt_present("summary") && matches.opt_present("porcelain") {
+                Err(Conflict("summary", "porcelain"))
+            }
+            else if matches.opt_present("html") && matches.opt_present("format") {
+                Err(Conflict("html", "format"))
+            }
+            else if matches.opt_present("html") && matches.opt_present("porcelain") {
+                Err(Conflict("html", "porcelain"))
+            }
             else {
+                let format = match matches.opt_str("format") {
+                    None => TableFormat::Padded,
+                    Some(word) => match &*word {
+                        "csv"      => TableFormat::Delimited(','),
+                        "tsv"      => TableFormat::Delimited('\t'),
+                        otherwise  => return Err(Misfire::bad_argument("format", otherwise)),
+                    },
+                };
+
                 let term_colours = try!(TerminalColours::deduce(matches));
                 let colours = match term_colours {
-                    TerminalColours::Always    => Colours::colourful(),
+                    _ if format != TableFormat::Padded  => Colours::plain(),
+                    _ if matches.opt_present("porcelain") => Colours::plain(),
+                    _ if matches.opt_present("html")      => Colours::plain(),
+                    TerminalColours::Always    => try!(colourful(matches, &theme)),
                     TerminalColours::Never     => Colours::plain(),
                     TerminalColours::Automatic => {
                         if dimensions().is_some() {
-                            Colours::colourful()
+                            try!(colourful(matches, &theme))
                         }
                         else {
                             Colours::plain()
@@ -175,12 +301,48 @@ impl View {
                     },
                 };
 
+                let total_size = match matches.opt_default("total-size", "apparent") {
+                    None => None,
+                    Some(word) => match &*word {
+                        "apparent"  => Some(TotalSize::Apparent),
+                        "disk"      => Some(TotalSize::OnDisk),
+                        otherwise   => return Err(Misfire::bad_argument("total-size", otherwise)),
+                    },
+                };
+
+                let porcelain = matches.opt_present("porcelain");
+
+                // The porcelain format always has the same columns, so
+                // scripts can rely on which field is which.
+                let columns = if porcelain {
+                    for option in &[ "inode", "links", "blocks", "group", "sniff" ] {
+                        if matches.opt_present(option) {
+                            return Err(Conflict("porcelain", *option));
+                        }
+                    }
+
+                    if cfg!(feature="git") && matches.opt_present("git") {
+                        return Err(Conflict("porcelain", "git"));
+                    }
+
+                    Columns { size_format: SizeFormat::JustBytes, time_types: TimeTypes::default(), .. Columns::default() }
+                }
+                else {
+                    try!(Columns::deduce(matches))
+                };
+
                 let details = Details {
-                    columns: Some(try!(Columns::deduce(matches))),
+                    columns: Some(columns),
                     header: matches.opt_present("header"),
                     recurse: dir_action.recurse_options(),
-                    filter: filter,
+                    filter: filter.clone(),
                     xattr: xattr::ENABLED && matches.opt_present("extended"),
+                    total_size: total_size,
+                    path_format: path_format.clone(),
+                    porcelain: porcelain,
+                    format: format,
+                    html: matches.opt_present("html"),
+                    tree_style: tree_style,
                     colours: colours,
                 };
 
@@ -189,12 +351,18 @@ impl View {
         };
 
         let long_options_scan = || {
-            for option in &[ "binary", "bytes", "inode", "links", "header", "blocks", "time", "group" ] {
+            for option in &[ "binary", "bytes", "inode", "links", "header", "blocks", "group", "total-size", "format" ] {
                 if matches.opt_present(option) {
                     return Err(Useless(option, false, "long"));
                 }
             }
 
+            // The time option also picks which timestamp the age filter
+            // compares against, so it's only useless without either.
+            if matches.opt_present("time") && !matches.opt_present("newer") && !matches.opt_present("older") {
+                return Err(Useless("time", false, "long"));
+            }
+
             if cfg!(feature="git") && matches.opt_present("git") {
                 Err(Useless("git", false, "long"))
             }
@@ -210,14 +378,19 @@ impl View {
         };
 
         let other_options_scan = || {
+            let html = matches.opt_present("html");
             let term_colours = try!(TerminalColours::deduce(matches));
-            let term_width   = try!(TerminalWidth::deduce(matches));
+
+            // HTML output has no terminal width to fit a grid into, so it
+            // always gets displayed as lines.
+            let term_width = if html { TerminalWidth::Unset }
+                                else { try!(TerminalWidth::deduce(matches)) };
 
             if let TerminalWidth::Set(width) = term_width {
                 let colours = match term_colours {
-                    TerminalColours::Always    => Colours::colourful(),
+                    TerminalColours::Always    => try!(colourful(matches, &theme)),
                     TerminalColours::Never     => Colours::plain(),
-                    TerminalColours::Automatic => Colours::colourful(),
+                    TerminalColours::Automatic => try!(colourful(matches, &theme)),
                 };
 
                 if matches.opt_present("oneline") {
@@ -227,6 +400,8 @@ impl View {
                     else {
                         let lines = Lines {
                              colours: colours,
+                             path_format: path_format.clone(),
+                             html: html,
                         };
 
                         Ok(View::Lines(lines))
@@ -237,8 +412,14 @@ impl View {
                         columns: None,
                         header: false,
                         recurse: dir_action.recurse_options(),
-                        filter: filter,
+                        filter: filter.clone(),
                         xattr: false,
+                        total_size: None,
+                        path_format: path_format.clone(),
+                        porcelain: false,
+                        format: TableFormat::Padded,
+                        html: html,
+                        tree_style: tree_style,
                         colours: colours,
                     };
 
@@ -249,6 +430,7 @@ impl View {
                         across: matches.opt_present("across"),
                         console_width: width,
                         colours: colours,
+                        path_format: path_format.clone(),
                     };
 
                     Ok(View::Grid(grid))
@@ -260,7 +442,8 @@ impl View {
                 // fallback to the lines view.
 
                 let colours = match term_colours {
-                    TerminalColours::Always    => Colours::colourful(),
+                    _ if html                  => Colours::plain(),
+                    TerminalColours::Always    => try!(colourful(matches, &theme)),
                     TerminalColours::Never     => Colours::plain(),
                     TerminalColours::Automatic => Colours::plain(),
                 };
@@ -270,8 +453,14 @@ impl View {
                         columns: None,
                         header: false,
                         recurse: dir_action.recurse_options(),
-                        filter: filter,
+                        filter: filter.clone(),
                         xattr: false,
+                        total_size: None,
+                        path_format: path_format.clone(),
+                        porcelain: false,
+                        format: TableFormat::Padded,
+                        html: html,
+                        tree_style: tree_style,
                         colours: colours,
                     };
 
@@ -280,6 +469,8 @@ impl View {
                 else {
                     let lines = Lines {
                          colours: colours,
+                         path_format: path_format.clone(),
+                         html: html,
                     };
 
                     Ok(View::Lines(lines))
@@ -287,7 +478,89 @@ impl View {
             }
         };
 
-        if matches.opt_present("long") {
+        if let Some(word) = matches.opt_default("count", "kinds") {
+            for option in &[ "long", "grid", "oneline", "across", "tree", "print0", "html", "summary" ] {
+                if matches.opt_present(option) {
+                    return Err(Conflict("count", *option));
+                }
+            }
+
+            try!(long_options_scan());
+
+            let categories = match &*word {
+                "kinds"       => false,
+                "categories"  => true,
+                otherwise     => return Err(Misfire::bad_argument("count", otherwise)),
+            };
+
+            let json = matches.opt_present("json");
+
+            let use_colours = match try!(TerminalColours::deduce(matches)) {
+                _ if json                  => false,
+                TerminalColours::Always    => true,
+                TerminalColours::Never     => false,
+                TerminalColours::Automatic => dimensions().is_some(),
+            };
+
+            // The categories are needed to count the files in them, even
+            // when they don’t get used for colours.
+            let mut colours = Colours::plain();
+            if use_colours {
+                colours = try!(colourful(matches, &theme));
+            }
+            else if categories {
+                colours.classifier = try!(Classifier::deduce(matches));
+                colours.classifier.remove_styles();
+            }
+
+            let count = Count {
+                categories: categories,
+                json: json,
+                colours: colours,
+            };
+
+            return Ok(View::Count(count));
+        }
+
+        if matches.opt_present("json") {
+            for option in &[ "long", "grid", "oneline", "across", "print0", "html", "summary", "sniff" ] {
+                if matches.opt_present(option) {
+                    return Err(Conflict("json", *option));
+                }
+            }
+
+            try!(long_options_scan());
+
+            let json = Json {
+                recurse: dir_action.recurse_options(),
+                filter: filter.clone(),
+            };
+
+            return Ok(View::Json(json));
+        }
+
+        if matches.opt_present("print0") {
+            for option in &[ "long", "grid", "oneline", "across", "html", "summary", "sniff" ] {
+                if matches.opt_present(option) {
+                    return Err(Conflict("print0", *option));
+                }
+            }
+
+            try!(long_options_scan());
+
+            let print0 = Print0 {
+                recurse: dir_action.recurse_options(),
+                filter: filter.clone(),
+            };
+
+            return Ok(View::Print0(print0));
+        }
+
+        if matches.opt_present("html") && matches.opt_present("grid") {
+            return Err(Conflict("html", "grid"));
+        }
+
+        if matches.opt_present("long") || matches.opt_present("porcelain") {
             let long_options = try!(long());
 
             if matches.opt_present("grid") {
@@ -314,26 +587,95 @@ trait OptionSet: Sized {
 }
 
 
+impl OptionSet for TreeStyle {
+
+    /// Determine which characters to draw the tree with. A Markdown list
+    /// can’t have columns in front of its bullets, so that style can’t be
+    /// used with either of the views that add them.
+    fn deduce(matches: &getopts::Matches) -> Result<TreeStyle, Misfire> {
+        match matches.opt_str("tree-style") {
+            None => Ok(TreeStyle::default()),
+            Some(word) => match &*word {
+                "unicode"   => Ok(TreeStyle::Unicode),
+                "ascii"     => Ok(TreeStyle::Ascii),
+                "markdown"  => {
+                    for option in &[ "long", "porcelain" ] {
+                        if matches.opt_present(option) {
+                            return Err(Misfire::Conflict("tree-style", *option));
+                        }
+                    }
+
+                    Ok(TreeStyle::Markdown)
+                },
+                otherwise   => Err(Misfire::bad_argument("tree-style", otherwise)),
+            },
+        }
+    }
+}
+
+
+impl OptionSet for PathFormat {
+
+    /// Determine how to display file names. The directory that paths are
+    /// relative to gets canonicalised up front, as the paths of the files
+    /// will be canonicalised before being compared to it.
+    fn deduce(matches: &getopts::Matches) -> Result<PathFormat, Misfire> {
+        match (matches.opt_present("absolute"), matches.opt_str("relative-to")) {
+            (true,  Some(_))    => Err(Misfire::Conflict("absolute", "relative-to")),
+            (true,  None)       => Ok(PathFormat::Absolute),
+            (false, None)       => Ok(PathFormat::Bare),
+            (false, Some(dir))  => match fs::canonicalize(&dir) {
+                Ok(path)  => Ok(PathFormat::RelativeTo(path)),
+                Err(_)    => Err(Misfire::bad_argument("relative-to", &dir)),
+            },
+        }
+    }
+}
+
+
 /// The **file filter** processes a vector of files before outputting them,
 /// filtering and sorting the files depending on the user’s command-line
 /// flags.
-#[derive(Default, PartialEq, Debug, Copy, Clone)]
+#[derive(Default, PartialEq, Debug, Clone)]
 pub struct FileFilter {
     list_dirs_first: bool,
+    dotfile_placement: DotfilePlacement,
     reverse: bool,
-    show_invisibles: bool,
-    sort_field: SortField,
+    pub dot_filter: DotFilter,
+    sort_keys: SortKeys,
+    ignore_patterns: IgnorePatterns,
+    git_ignore: bool,
+    kind_filter: KindFilter,
+    size_filter: SizeFilter,
+    age_filter: AgeFilter,
+
+    /// Whether to read the start of each file to work out its format,
+    /// which decides its colour, and goes in the long view’s Type column.
+    sniff: bool,
 }
 
 impl OptionSet for FileFilter {
     fn deduce(matches: &getopts::Matches) -> Result<FileFilter, Misfire> {
-        let sort_field = try!(SortField::deduce(&matches));
+        let sort_keys = try!(SortKeys::deduce(&matches));
+        let dotfile_placement = try!(DotfilePlacement::deduce(&matches));
+        let dot_filter = try!(DotFilter::deduce(&matches));
+        let ignore_patterns = try!(IgnorePatterns::deduce(&matches));
+        let kind_filter = try!(KindFilter::deduce(&matches));
+        let size_filter = try!(SizeFilter::deduce(&matches));
+        let age_filter = try!(AgeFilter::deduce(&matches));
 
         Ok(FileFilter {
             list_dirs_first: matches.opt_present("group-directories-first"),
+            dotfile_placement: dotfile_placement,
             reverse:         matches.opt_present("reverse"),
-            show_invisibles: matches.opt_present("all"),
-            sort_field:      sort_field,
+            dot_filter:      dot_filter,
+            sort_keys:       sort_keys,
+            ignore_patterns: ignore_patterns,
+            git_ignore:      cfg!(feature="git") && matches.opt_present("git-ignore"),
+            kind_filter:     kind_filter,
+            size_filter:     size_filter,
+            age_filter:      age_filter,
+            sniff:           matches.opt_present("sniff"),
         })
     }
 }
@@ -343,43 +685,419 @@ impl FileFilter {
     /// Remove every file in the given vector that does *not* pass the
     /// filter predicate.
     pub fn filter_files(&self, files: &mut Vec<File>) {
-        if !self.show_invisibles {
+        if let DotFilter::JustFiles = self.dot_filter {
             files.retain(|f| !f.is_dotfile());
         }
+
+        files.retain(|f| !self.ignore_patterns.is_ignored(f));
+
+        if self.git_ignore {
+            files.retain(|f| !f.is_git_ignored());
+        }
     }
 
-    /// Sort the files in the given vector based on the sort field option.
+    /// Whether the given file passes the predicates that decide which files
+    /// get *displayed*, such as the kinds of file to list, or their sizes
+    /// and ages.
+    ///
+    /// Unlike the files removed by `filter_files`, a directory that fails
+    /// these predicates should still be recursed into, as there may be
+    /// files inside it that pass them.
+    pub fn matches(&self, file: &File) -> bool {
+        self.kind_filter.matches(file)
+            && self.size_filter.matches(file)
+            && self.age_filter.matches(file)
+    }
+
+    /// Whether the directories being listed need their Git repositories
+    /// scanned, so that ignored files can be filtered out, or files can be
+    /// sorted by their status.
+    pub fn should_scan_for_git(&self) -> bool {
+        self.git_ignore || self.sort_keys.keys.iter().any(|k| k.field == SortField::GitStatus)
+    }
+
+    /// Whether the files’ contents get read to work out their formats.
+    pub fn sniffs(&self) -> bool {
+        self.sniff
+    }
+
+    /// Read the start of each regular file in the given vector to work out
+    /// its format, if the user asked for it. Every listing gets this done
+    /// to it after being filtered and sorted, whichever view shows it.
+    pub fn sniff_files(&self, files: &mut [File]) {
+        if self.sniff {
+            sniff_files(&mut Pool::new(num_cpus::get() as u32), files);
+        }
+    }
+
+    /// Whether the files get sorted by their size, so would need sorting
+    /// again after the sizes of directories have been calculated.
+    pub fn sorts_by_size(&self) -> bool {
+        self.sort_keys.keys.iter().any(|k| k.field == SortField::Size)
+    }
+
+    /// Sort the files in the given vector based on the sort keys option.
+    ///
+    /// Finding a file’s Git status means searching through its repository’s
+    /// statuses, so when sorting by it, each file’s rank gets worked out
+    /// once beforehand, rather than every time two files get compared.
     pub fn sort_files(&self, files: &mut Vec<File>) {
-        files.sort_by(|a, b| self.compare_files(a, b));
+        if self.sort_keys.keys.iter().any(|k| k.field == SortField::GitStatus) {
+            let mut ranked: Vec<(usize, File)> = files.drain(..).map(|f| (git_rank(&f), f)).collect();
+            ranked.sort_by(|a, b| self.compare_files(&a.1, &b.1, Some((a.0, b.0))));
+            files.extend(ranked.into_iter().map(|(_, f)| f));
+        }
+        else {
+            files.sort_by(|a, b| self.compare_files(a, b, None));
+        }
 
         if self.reverse {
             files.reverse();
         }
 
+        match self.dotfile_placement {
+            DotfilePlacement::Mixed  => {},
+            DotfilePlacement::First  => files.sort_by(|a, b| b.is_dotfile().cmp(&a.is_dotfile())),
+            DotfilePlacement::Last   => files.sort_by(|a, b| a.is_dotfile().cmp(&b.is_dotfile())),
+        }
+
         if self.list_dirs_first {
             // This relies on the fact that `sort_by` is stable.
             files.sort_by(|a, b| b.is_directory().cmp(&a.is_directory()));
         }
     }
 
-    pub fn compare_files(&self, a: &File, b: &File) -> cmp::Ordering {
-        match self.sort_field {
-            SortField::Unsorted      => cmp::Ordering::Equal,
-            SortField::Name          => natord::compare(&*a.name, &*b.name),
-            SortField::Size          => a.metadata.len().cmp(&b.metadata.len()),
-            SortField::FileInode     => a.metadata.ino().cmp(&b.metadata.ino()),
-            SortField::ModifiedDate  => a.metadata.mtime().cmp(&b.metadata.mtime()),
-            SortField::AccessedDate  => a.metadata.atime().cmp(&b.metadata.atime()),
-            SortField::CreatedDate   => a.metadata.ctime().cmp(&b.metadata.ctime()),
-            SortField::Extension     => match a.ext.cmp(&b.ext) {
-                cmp::Ordering::Equal  => natord::compare(&*a.name, &*b.name),
-                order                 => order,
+    /// Compare two files using each of the sort keys in turn, moving on to
+    /// the next key only when the files are equal under the current one.
+    /// Their Git ranks get used if they’ve already been worked out.
+    fn compare_files(&self, a: &File, b: &File, git_ranks: Option<(usize, usize)>) -> cmp::Ordering {
+        for key in &self.sort_keys.keys {
+            let order = key.field.compare_files(a, b, git_ranks);
+            let order = if key.reverse { order.reverse() } else { order };
+
+            if order != cmp::Ordering::Equal {
+                return order;
+            }
+        }
+
+        cmp::Ordering::Equal
+    }
+}
+
+
+impl OptionSet for DotFilter {
+
+    /// Determine which dotfiles to show based on how many times `--all` was
+    /// given. Listing the `.` and `..` entries in a tree view would only
+    /// produce a loop of directories that are already on screen, so that
+    /// combination is disallowed.
+    fn deduce(matches: &getopts::Matches) -> Result<DotFilter, Misfire> {
+        match matches.opt_count("all") {
+            0 => Ok(DotFilter::JustFiles),
+            1 => Ok(DotFilter::Dotfiles),
+            _ => {
+                if matches.opt_present("tree") {
+                    Err(Misfire::Useless("all --all", true, "tree"))
+                }
+                else {
+                    Ok(DotFilter::DotfilesAndDots)
+                }
             },
         }
     }
 }
 
 
+/// The **ignore patterns** are a list of globs that are tested against
+/// each filename, and if any of them match, that file isn’t displayed.
+/// This lets a user hide, say, build artifacts or backup files, without
+/// having to resort to piping exa’s output through `grep`.
+#[derive(PartialEq, Default, Debug, Clone)]
+struct IgnorePatterns {
+    patterns: Vec<glob::Pattern>,
+}
+
+impl OptionSet for IgnorePatterns {
+
+    /// Compile the glob patterns given with each `--ignore-glob` option.
+    ///
+    /// The option can be given more than once, and each argument can
+    /// contain several patterns separated by pipes, such as `*.o|target`.
+    /// Any pattern that fails to compile is reported as an error, rather
+    /// than silently being left out.
+    fn deduce(matches: &getopts::Matches) -> Result<IgnorePatterns, Misfire> {
+        let mut patterns = Vec::new();
+
+        for globs in matches.opt_strs("ignore-glob") {
+            for word in globs.split('|').filter(|w| !w.is_empty()) {
+                match glob::Pattern::new(word) {
+                    Ok(pattern) => patterns.push(pattern),
+                    Err(e)      => return Err(Misfire::FailedGlobPattern(format!("{}: {}", word, e))),
+                }
+            }
+        }
+
+        Ok(IgnorePatterns { patterns: patterns })
+    }
+}
+
+impl IgnorePatterns {
+
+    /// Whether the given file’s name matches any of the patterns.
+    fn is_ignored(&self, file: &File) -> bool {
+        self.patterns.iter().any(|p| p.matches(&file.name))
+    }
+}
+
+
+/// The **kind filter** restricts the listing to only certain kinds of file,
+/// such as just directories or just regular files. An empty list of kinds
+/// means that every kind of file gets listed.
+#[derive(PartialEq, Default, Debug, Clone)]
+struct KindFilter {
+    kinds: Vec<FileKind>,
+}
+
+/// The kinds of file that can be picked out by the kind filter.
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum FileKind {
+    Directory, File, Link, Pipe, Socket, Device,
+}
+
+impl FileKind {
+    fn matches(&self, file: &File) -> bool {
+        match *self {
+            FileKind::Directory  => file.is_directory(),
+            FileKind::File       => file.is_file(),
+            FileKind::Link       => file.is_link(),
+            FileKind::Pipe       => file.is_pipe(),
+            FileKind::Socket     => file.is_socket(),
+            FileKind::Device     => file.is_device(),
+        }
+    }
+}
+
+impl OptionSet for KindFilter {
+
+    /// Determine which kinds of file to list. The `--only-dirs`,
+    /// `--only-files`, and `--only-links` flags are shorthands for the
+    /// most common kinds, and `--only` takes a comma-separated list of
+    /// any of them. Giving more than one lists files of *any* of the kinds.
+    fn deduce(matches: &getopts::Matches) -> Result<KindFilter, Misfire> {
+        let mut kinds = Vec::new();
+
+        if matches.opt_present("only-dirs")  { kinds.push(FileKind::Directory) }
+        if matches.opt_present("only-files") { kinds.push(FileKind::File) }
+        if matches.opt_present("only-links") { kinds.push(FileKind::Link) }
+
+        if let Some(words) = matches.opt_str("only") {
+            for word in words.split(',') {
+                let kind = match word {
+                    "dirs"  | "directories"  => FileKind::Directory,
+                    "files"                  => FileKind::File,
+                    "links" | "symlinks"     => FileKind::Link,
+                    "pipes" | "fifos"        => FileKind::Pipe,
+                    "sockets"                => FileKind::Socket,
+                    "devices"                => FileKind::Device,
+                    otherwise                => return Err(Misfire::bad_argument("only", otherwise)),
+                };
+
+                kinds.push(kind);
+            }
+        }
+
+        Ok(KindFilter { kinds: kinds })
+    }
+}
+
+impl KindFilter {
+    fn matches(&self, file: &File) -> bool {
+        self.kinds.is_empty() || self.kinds.iter().any(|k| k.matches(file))
+    }
+}
+
+
+/// The **size filter** drops files that are smaller or larger than the
+/// given bounds. Directories don't have a size, so they never pass it when
+/// either bound is set.
+#[derive(PartialEq, Default, Debug, Copy, Clone)]
+struct SizeFilter {
+    min: Option<u64>,
+    max: Option<u64>,
+}
+
+impl OptionSet for SizeFilter {
+    fn deduce(matches: &getopts::Matches) -> Result<SizeFilter, Misfire> {
+        let bound = |option: &'static str| {
+            match matches.opt_str(option) {
+                None => Ok(None),
+                Some(word) => match parse_size(&word) {
+                    Some(size)  => Ok(Some(size)),
+                    None        => Err(Misfire::FailedSizeParse(option, word)),
+                },
+            }
+        };
+
+        Ok(SizeFilter {
+            min: try!(bound("min-size")),
+            max: try!(bound("max-size")),
+        })
+    }
+}
+
+impl SizeFilter {
+    fn matches(&self, file: &File) -> bool {
+        if self.min.is_none() && self.max.is_none() {
+            return true;
+        }
+
+        if file.is_directory() {
+            return false;
+        }
+
+        let size = file.metadata.len();
+        self.min.map_or(true, |min| size >= min) && self.max.map_or(true, |max| size <= max)
+    }
+}
+
+/// Parse a human-friendly file size, such as `500`, `10M`, or `1.5GiB`.
+///
+/// Sizes without a suffix are in bytes. The suffixes use decimal prefixes
+/// (`k`, `M`, `G`, `T`) or binary ones (`Ki`, `Mi`, `Gi`, `Ti`), just like
+/// the size column does, and can optionally end with a `B`.
+fn parse_size(input: &str) -> Option<u64> {
+    let split = input.find(|c: char| !c.is_digit(10) && c != '.').unwrap_or(input.len());
+    let (number, suffix) = input.split_at(split);
+
+    let number: f64 = match number.parse() {
+        Ok(n)   => n,
+        Err(_)  => return None,
+    };
+
+    let multiplier: u64 = match suffix.trim_right_matches('B') {
+        ""         => 1,
+        "k" | "K"  => 1000,
+        "M"        => 1000 * 1000,
+        "G"        => 1000 * 1000 * 1000,
+        "T"        => 1000 * 1000 * 1000 * 1000,
+        "Ki"       => 1024,
+        "Mi"       => 1024 * 1024,
+        "Gi"       => 1024 * 1024 * 1024,
+        "Ti"       => 1024 * 1024 * 1024 * 1024,
+        _          => return None,
+    };
+
+    Some((number * multiplier as f64) as u64)
+}
+
+
+/// The **age filter** drops files with a timestamp before or after the
+/// given bounds, which are stored as a number of seconds since the epoch.
+/// The timestamp compared is the same one that gets displayed, picked with
+/// `--time` or one of the flags such as `--accessed`. If more than one gets
+/// displayed, the first of modified, accessed, and created is used.
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct AgeFilter {
+    time_type: TimeType,
+    newer_than: Option<i64>,
+    older_than: Option<i64>,
+}
+
+impl Default for AgeFilter {
+    fn default() -> AgeFilter {
+        AgeFilter { time_type: TimeType::Modified, newer_than: None, older_than: None }
+    }
+}
+
+impl OptionSet for AgeFilter {
+    fn deduce(matches: &getopts::Matches) -> Result<AgeFilter, Misfire> {
+        let now = Instant::now().seconds();
+
+        let bound = |option: &'static str| {
+            match matches.opt_str(option) {
+                None => Ok(None),
+                Some(word) => match parse_age(&word, now) {
+                    Some(time)  => Ok(Some(time)),
+                    None        => Err(Misfire::FailedTimeParse(option, word)),
+                },
+            }
+        };
+
+        let time_types = try!(TimeTypes::deduce(matches));
+        let time_type = if time_types.modified      { TimeType::Modified }
+                        else if time_types.accessed { TimeType::Accessed }
+                        else                        { TimeType::Created };
+
+        Ok(AgeFilter {
+            time_type:  time_type,
+            newer_than: try!(bound("newer")),
+            older_than: try!(bound("older")),
+        })
+    }
+}
+
+impl AgeFilter {
+    #[allow(trivial_numeric_casts)]
+    fn matches(&self, file: &File) -> bool {
+        if self.newer_than.is_none() && self.older_than.is_none() {
+            return true;
+        }
+
+        let time = match self.time_type {
+            TimeType::Modified  => file.modified_time(),
+            TimeType::Accessed  => file.accessed_time(),
+            TimeType::Created   => file.created_time(),
+        };
+
+        let time = time.0 as i64;
+        self.newer_than.map_or(true, |t| time > t) && self.older_than.map_or(true, |t| time < t)
+    }
+}
+
+/// Parse a human-friendly point in time, returning it as a number of
+/// seconds since the epoch.
+///
+/// This can either be a duration before the given current time, which is a
+/// number followed by a unit (`30s`, `15m`, `6h`, `2d`, `3w`, or `1y`), or
+/// an absolute ISO-8601 date or date-time in UTC, such as `2015-10-18` or
+/// `2015-10-18T12:30:00`. A duration too long to subtract from the current
+/// time can’t be parsed, and nor can a negative one, which would be in the
+/// future.
+fn parse_age(input: &str, now: i64) -> Option<i64> {
+    if let Some(unit) = input.chars().last() {
+        let number = &input[.. input.len() - unit.len_utf8()];
+
+        let seconds = match unit {
+            's' => Some(1),
+            'm' => Some(60),
+            'h' => Some(60 * 60),
+            'd' => Some(60 * 60 * 24),
+            'w' => Some(60 * 60 * 24 * 7),
+            'y' => Some(60 * 60 * 24 * 365),
+            _   => None,
+        };
+
+        if let (Some(seconds), Ok(count)) = (seconds, number.parse::<u64>()) {
+            if count > i64::max_value() as u64 {
+                return None;
+            }
+
+            return (count as i64).checked_mul(seconds).and_then(|s| now.checked_sub(s));
+        }
+    }
+
+    if let Some(date_time) = LocalDateTime::parse(input) {
+        Some(date_time.to_instant().seconds())
+    }
+    else if let Some(date) = LocalDate::parse(input) {
+        Some(LocalDateTime::from_date_time(date, LocalTime::midnight()).to_instant().seconds())
+    }
+    else {
+        None
+    }
+}
+
+
 /// What to do when encountering a directory?
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum DirAction {
@@ -459,7 +1177,9 @@ impl RecurseOptions {
 /// User-supplied field to sort by.
 #[derive(PartialEq, Debug, Copy, Clone)]
 pub enum SortField {
-    Unsorted, Name, Extension, Size, FileInode,
+    Unsorted, Extension, Size, FileInode,
+    Name, NameIgnoringCase, NameBytes, NameLocale,
+    FileType, Version, GitStatus,
     ModifiedDate, AccessedDate, CreatedDate,
 }
 
@@ -469,23 +1189,266 @@ impl Default for SortField {
     }
 }
 
-impl OptionSet for SortField {
-    fn deduce(matches: &getopts::Matches) -> Result<SortField, Misfire> {
+impl SortField {
+    fn from_word(word: &str) -> Result<SortField, Misfire> {
+        match word {
+            "Name" | "Filename"   => Ok(SortField::Name),
+            "name" | "filename"   => Ok(SortField::NameIgnoringCase),
+            "name-bytes"          => Ok(SortField::NameBytes),
+            "name-locale"         => Ok(SortField::NameLocale),
+            "size" | "filesize"   => Ok(SortField::Size),
+            "ext"  | "extension"  => Ok(SortField::Extension),
+            "mod"  | "modified"   => Ok(SortField::ModifiedDate),
+            "acc"  | "accessed"   => Ok(SortField::AccessedDate),
+            "cr"   | "created"    => Ok(SortField::CreatedDate),
+            "none"                => Ok(SortField::Unsorted),
+            "inode"               => Ok(SortField::FileInode),
+            "type"                => Ok(SortField::FileType),
+            "version"             => Ok(SortField::Version),
+            "git"                 => Ok(SortField::GitStatus),
+            field                 => Err(Misfire::bad_argument("sort", field))
+        }
+    }
+
+    /// Whether this field sorts by the file’s name, in any order.
+    fn is_name(&self) -> bool {
+        match *self {
+            SortField::Name | SortField::NameIgnoringCase
+          | SortField::NameBytes | SortField::NameLocale  => true,
+            _                                              => false,
+        }
+    }
+
+    fn compare_files(&self, a: &File, b: &File, git_ranks: Option<(usize, usize)>) -> cmp::Ordering {
+        match *self {
+            SortField::Unsorted      => cmp::Ordering::Equal,
+            SortField::Name              => compare_undotted(&*a.name, &*b.name, natord::compare),
+            SortField::NameIgnoringCase  => compare_undotted(&*a.name, &*b.name, natord::compare_ignore_case),
+            SortField::NameBytes         => a.name.as_bytes().cmp(b.name.as_bytes()),
+            SortField::NameLocale        => collate::compare(&*a.name, &*b.name),
+            SortField::Size          => sort_size(a).cmp(&sort_size(b)),
+            SortField::FileInode     => a.metadata.ino().cmp(&b.metadata.ino()),
+            SortField::ModifiedDate  => a.metadata.mtime().cmp(&b.metadata.mtime()),
+            SortField::AccessedDate  => a.metadata.atime().cmp(&b.metadata.atime()),
+            SortField::CreatedDate   => a.metadata.ctime().cmp(&b.metadata.ctime()),
+            SortField::Extension     => a.ext.cmp(&b.ext),
+            SortField::FileType      => type_rank(a).cmp(&type_rank(b)),
+            SortField::Version       => compare_versions(&*a.name, &*b.name),
+            SortField::GitStatus     => match git_ranks {
+                Some((a, b))  => a.cmp(&b),
+                None          => git_rank(a).cmp(&git_rank(b)),
+            },
+        }
+    }
+}
+
+
+/// Compare two file names with the given function, ignoring any leading
+/// dot, so that `.bashrc` gets sorted next to `bin`. Names that only differ
+/// by their dot are then compared as a whole.
+fn compare_undotted<F>(a: &str, b: &str, compare: F) -> cmp::Ordering
+where F: Fn(&str, &str) -> cmp::Ordering {
+    let a_undotted = if a.starts_with('.') { &a[1..] } else { a };
+    let b_undotted = if b.starts_with('.') { &b[1..] } else { b };
+
+    match compare(a_undotted, b_undotted) {
+        cmp::Ordering::Equal  => compare(a, b),
+        order                 => order,
+    }
+}
+
+/// The size of a file when sorting by size, which uses the total size of a
+/// directory’s contents if it’s been calculated.
+fn sort_size(file: &File) -> u64 {
+    file.total_size.unwrap_or(file.metadata.len())
+}
+
+/// The position of a file when sorting by type: directories come first,
+/// then regular files, then links, and then the more special files.
+fn type_rank(file: &File) -> usize {
+    if file.is_directory()     { 0 }
+    else if file.is_file()     { 1 }
+    else if file.is_link()     { 2 }
+    else if file.is_pipe()     { 3 }
+    else if file.is_socket()   { 4 }
+    else if file.is_device()   { 5 }
+    else                       { 6 }
+}
+
+/// The position of a file when sorting by Git status: files with changes,
+/// whether staged, unstaged, or untracked, come before unmodified ones.
+fn git_rank(file: &File) -> usize {
+    use file::fields::{Git, GitStatus};
+
+    match file.git_status() {
+        Git { staged: GitStatus::NotModified, unstaged: GitStatus::NotModified }  => 1,
+        _                                                                        => 0,
+    }
+}
+
+/// Compare two file names containing version numbers, such as `v1.9.3` and
+/// `v1.10.0`.
+///
+/// Names are split into runs of digits and runs of everything else. Digit
+/// runs get compared by their numeric value, with fewer leading zeroes
+/// breaking a tie, and other runs get compared as text. As with semantic
+/// versioning, a pre-release part starting with a `-`, such as the `-rc1`
+/// in `v2.0.0-rc1`, sorts before the release it leads up to.
+fn compare_versions(a: &str, b: &str) -> cmp::Ordering {
+    let mut a_chunks = VersionChunks { input: a };
+    let mut b_chunks = VersionChunks { input: b };
+
+    loop {
+        let order = match (a_chunks.next(), b_chunks.next()) {
+            (None, None)        => return cmp::Ordering::Equal,
+            (Some(a), None)     => if a.starts_with('-') { cmp::Ordering::Less } else { cmp::Ordering::Greater },
+            (None, Some(b))     => if b.starts_with('-') { cmp::Ordering::Greater } else { cmp::Ordering::Less },
+            (Some(a), Some(b))  => compare_version_chunks(a, b),
+        };
+
+        if order != cmp::Ordering::Equal {
+            return order;
+        }
+    }
+}
+
+fn compare_version_chunks(a: &str, b: &str) -> cmp::Ordering {
+    let a_numeric = a.starts_with(|c: char| c.is_digit(10));
+    let b_numeric = b.starts_with(|c: char| c.is_digit(10));
+
+    match (a_numeric, b_numeric) {
+        (true, true) => {
+            let a_trimmed = a.trim_left_matches('0');
+            let b_trimmed = b.trim_left_matches('0');
+
+            match a_trimmed.len().cmp(&b_trimmed.len()) {
+                cmp::Ordering::Equal => match a_trimmed.cmp(b_trimmed) {
+                    cmp::Ordering::Equal  => b.len().cmp(&a.len()),
+                    order                 => order,
+                },
+                order => order,
+            }
+        },
+        (true, false)  => cmp::Ordering::Less,
+        (false, true)  => cmp::Ordering::Greater,
+        (false, false) => {
+            match (a.starts_with('-'), b.starts_with('-')) {
+                (true, false)  => cmp::Ordering::Less,
+                (false, true)  => cmp::Ordering::Greater,
+                _              => a.cmp(b),
+            }
+        },
+    }
+}
+
+/// Iterator over the runs of digits and non-digits in a version string.
+struct VersionChunks<'a> {
+    input: &'a str,
+}
+
+impl<'a> Iterator for VersionChunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        let numeric = match self.input.chars().next() {
+            Some(c)  => c.is_digit(10),
+            None     => return None,
+        };
+
+        let end = self.input.find(|c: char| c.is_digit(10) != numeric).unwrap_or(self.input.len());
+        let (chunk, rest) = self.input.split_at(end);
+        self.input = rest;
+        Some(chunk)
+    }
+}
+
+
+/// Where to put dotfiles in relation to the other files.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum DotfilePlacement {
+
+    /// Sort dotfiles among the other files.
+    Mixed,
+
+    /// List dotfiles before the other files.
+    First,
+
+    /// List dotfiles after the other files.
+    Last,
+}
+
+impl Default for DotfilePlacement {
+    fn default() -> DotfilePlacement {
+        DotfilePlacement::Mixed
+    }
+}
+
+impl OptionSet for DotfilePlacement {
+    fn deduce(matches: &getopts::Matches) -> Result<DotfilePlacement, Misfire> {
+        match matches.opt_str("group-dotfiles") {
+            None => Ok(DotfilePlacement::default()),
+            Some(word) => match &*word {
+                "mixed"    => Ok(DotfilePlacement::Mixed),
+                "first"    => Ok(DotfilePlacement::First),
+                "last"     => Ok(DotfilePlacement::Last),
+                otherwise  => Err(Misfire::bad_argument("group-dotfiles", otherwise)),
+            },
+        }
+    }
+}
+
+
+/// One of the fields to sort by, along with whether it should be sorted
+/// in reverse.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct SortKey {
+    field: SortField,
+    reverse: bool,
+}
+
+/// The list of keys to sort by, in order of priority. Files that compare
+/// equal on one key get compared on the next, so that files with the same
+/// size or timestamp still end up in a predictable order.
+#[derive(PartialEq, Debug, Clone)]
+pub struct SortKeys {
+    keys: Vec<SortKey>,
+}
+
+impl Default for SortKeys {
+    fn default() -> SortKeys {
+        SortKeys { keys: vec![ SortKey { field: SortField::default(), reverse: false } ] }
+    }
+}
+
+impl OptionSet for SortKeys {
+
+    /// Determine the sort keys from a comma-separated list of fields, each
+    /// of which can be prefixed with a `-` to sort by it in reverse. Unless
+    /// the name or `none` is already one of the keys, the name is added as
+    /// a final key to break any remaining ties.
+    fn deduce(matches: &getopts::Matches) -> Result<SortKeys, Misfire> {
         if let Some(word) = matches.opt_str("sort") {
-            match &*word {
-                "name" | "filename"   => Ok(SortField::Name),
-                "size" | "filesize"   => Ok(SortField::Size),
-                "ext"  | "extension"  => Ok(SortField::Extension),
-                "mod"  | "modified"   => Ok(SortField::ModifiedDate),
-                "acc"  | "accessed"   => Ok(SortField::AccessedDate),
-                "cr"   | "created"    => Ok(SortField::CreatedDate),
-                "none"                => Ok(SortField::Unsorted),
-                "inode"               => Ok(SortField::FileInode),
-                field                 => Err(Misfire::bad_argument("sort", field))
+            let mut keys = Vec::new();
+
+            for field in word.split(',') {
+                let key = if field.starts_with('-') {
+                    SortKey { field: try!(SortField::from_word(&field[1..])), reverse: true }
+                }
+                else {
+                    SortKey { field: try!(SortField::from_word(field)), reverse: false }
+                };
+
+                keys.push(key);
+            }
+
+            if !keys.iter().any(|k| k.field.is_name() || k.field == SortField::Unsorted) {
+                keys.push(SortKey { field: SortField::Name, reverse: false });
             }
+
+            Ok(SortKeys { keys: keys })
         }
         else {
-            Ok(SortField::default())
+            Ok(SortKeys::default())
         }
     }
 }
@@ -545,6 +1508,58 @@ impl OptionSet for TerminalColours {
 }
 
 
+impl OptionSet for LsColors {
+
+    /// Read the file name colours from the `LS_COLORS` environment
+    /// variable, which is shared with `ls` and other programs. Entries that
+    /// can’t be parsed get ignored, rather than stopping exa from running.
+    fn deduce(_: &getopts::Matches) -> Result<LsColors, Misfire> {
+        match var_os("LS_COLORS").and_then(|s| s.into_string().ok()) {
+            Some(input)  => Ok(LsColors::parse(&input)),
+            None         => Ok(LsColors::default()),
+        }
+    }
+}
+
+impl OptionSet for Classifier {
+
+    /// Read the categories that decide the colours of files from the
+    /// user’s configuration directory and the current directory.
+    fn deduce(_: &getopts::Matches) -> Result<Classifier, Misfire> {
+        Classifier::load().map_err(Misfire::FailedCategories)
+    }
+}
+
+/// The colours to use when the output is colourful: the built-in palette,
+/// with any colours from the given theme and the environment taking priority.
+/// Those in the theme and in `EXA_COLORS` go on top of those in
+/// `LS_COLORS`, as they’re only for exa.
+fn colourful(matches: &getopts::Matches, theme: &Option<Theme>) -> Result<Colours, Misfire> {
+    let mut colours = Colours::colourful();
+    colours.ls_colors = try!(LsColors::deduce(matches));
+    colours.classifier = try!(Classifier::deduce(matches));
+
+    if let Some(ref theme) = *theme {
+        try!(colours.set_theme(theme.clone()).map_err(Misfire::FailedTheme));
+    }
+
+    if let Some(input) = var_os("EXA_COLORS").and_then(|s| s.into_string().ok()) {
+        if let Err(entry) = colours.set_exa_colors(&input) {
+            return Err(Misfire::FailedColourParse("EXA_COLORS", entry));
+        }
+    }
+
+    Ok(colours)
+}
+
+/// Load the theme named with `--theme`, or the user’s default theme if
+/// there isn’t one.
+fn theme(matches: &getopts::Matches) -> Result<Option<Theme>, Misfire> {
+    let name = matches.opt_str("theme");
+    Theme::load(name.as_ref().map(|n| &n[..])).map_err(Misfire::FailedTheme)
+}
+
+
 impl OptionSet for Columns {
     fn deduce(matches: &getopts::Matches) -> Result<Columns, Misfire> {
         Ok(Columns {
@@ -657,6 +1672,26 @@ pub enum Misfire {
 
     /// A numeric option was given that failed to be parsed as a number.
     FailedParse(ParseIntError),
+
+    /// A glob ignore was given that failed to be parsed as a pattern.
+    FailedGlobPattern(String),
+
+    /// A size option was given that failed to be parsed as a file size.
+    FailedSizeParse(&'static str, String),
+
+    /// A time option was given that failed to be parsed as either a
+    /// duration or a date.
+    FailedTimeParse(&'static str, String),
+
+    /// An entry in an environment variable that sets colours couldn't be
+    /// parsed, either because its key or its style is wrong.
+    FailedColourParse(&'static str, String),
+
+    /// The theme file couldn't be read, or has something wrong with it.
+    FailedTheme(String),
+
+    /// A file of categories couldn't be read, or has something wrong with it.
+    FailedCategories(String),
 }
 
 impl Misfire {
@@ -689,6 +1724,12 @@ impl fmt::Display for Misfire {
             Useless(a, true, b)    => write!(f, "Option --{} is useless given option --{}.", a, b),
             Useless2(a, b1, b2)    => write!(f, "Option --{} is useless without options --{} or --{}.", a, b1, b2),
             FailedParse(ref e)     => write!(f, "Failed to parse number: {}", e),
+            FailedGlobPattern(ref e) => write!(f, "Failed to parse glob pattern: {}", e),
+            FailedSizeParse(a, ref e)  => write!(f, "Failed to parse size for option --{}: {}", a, e),
+            FailedTimeParse(a, ref e)  => write!(f, "Failed to parse time for option --{}: {}", a, e),
+            FailedColourParse(a, ref e) => write!(f, "Failed to parse colour in {}: {}", a, e),
+            FailedTheme(ref e)     => write!(f, "Failed to load theme: {}", e),
+            FailedCategories(ref e) => write!(f, "Failed to load file categories: {}", e),
         }
     }
 }
@@ -701,14 +1742,42 @@ DISPLAY OPTIONS
   -R, --recurse      recurse into directories
   -T, --tree         recurse into subdirectories in a tree view
   -x, --across       sort multi-column view entries across
+  --tree-style STYLE which characters to draw the tree with
+                       (unicode, ascii, markdown)
+  --json             display details of each file as JSON
+  -0, --print0       display bare paths separated by NUL bytes
+  --html             display the listing as HTML, with CSS classes for colours
+  --sniff            colour each file by its type, read from its contents
+  --count[=WHAT]     display how many files there are instead of listing them
+                       (kinds, categories)
   --color, --colour  when to colourise the output
+  --theme NAME       which theme from the configuration directory to use
+                       (#rrggbb colours get the closest of the 256 colours)
+  --absolute         display the full path of each file
+  --relative-to DIR  display each file's path relative to a directory
+  --summary          display the totals of each directory after its listing
 
 FILTERING AND SORTING OPTIONS
-  -a, --all                  show dot-files
+  -a, --all                  show dot-files (twice to also show . and ..)
   -d, --list-dirs            list directories as regular files
+  -I, --ignore-glob GLOBS    ignore files that match these glob patterns
   -r, --reverse              reverse order of files
-  -s, --sort WORD            field to sort by
+  --only-dirs                list only directories
+  --only-files               list only regular files
+  --only-links               list only symbolic links
+  --only KINDS               list only these kinds of file (dirs, files,
+                             links, pipes, sockets, devices)
+  --min-size SIZE            list only files at least this big (e.g. 10M)
+  --max-size SIZE            list only files at most this big
+  --newer TIME               list only files changed after this time
+                             (e.g. 2d, or 2015-10-18)
+  --older TIME               list only files changed before this time
+  -s, --sort WORDS           fields to sort by, separated by commas,
+                             with a - before a field to reverse it
+                             (name ignores case; Name, the default,
+                             puts capitals first)
   --group-directories-first  list directories before other files
+  --group-dotfiles WHERE     list dotfiles first, last, or mixed in
 "##;
 
 static LONG_OPTIONS: &'static str = r##"
@@ -722,11 +1791,19 @@ LONG VIEW OPTIONS
   -L, --level DEPTH  maximum depth of recursion
   -m, --modified     display timestamp of most recent modification
   -S, --blocks       show number of file system blocks
+  --total-size       show the total size of directories' contents
+                     (use --total-size=disk for space used on disk)
+  --sniff            also show the type of each file in a column
+  --format WORD      print the table as csv or tsv records
+  --porcelain        use a format that's stable for scripts: the same columns
+                     every time, unpadded, with no colours, sizes in bytes,
+                     and ISO-8601 timestamps (implies -l)
   -t, --time WORD    which timestamp to show for a file
   -u, --accessed     display timestamp of last access for a file
   -U, --created      display timestamp of creation for a file
 "##;
 
+static GIT_FILTER_HELP: &'static str = r##"  --git-ignore               ignore files mentioned in '.gitignore'"##;
 static GIT_HELP:      &'static str = r##"  --git              show git status for files"##;
 static EXTENDED_HELP: &'static str = r##"  -@, --extended     display extended attribute keys and sizes"##;
 
@@ -734,7 +1811,11 @@ static EXTENDED_HELP: &'static str = r##"  -@, --extended     display extended a
 mod test {
     use super::Options;
     use super::Misfire;
+    use super::{parse_age, parse_size};
+    use output::column::TimeType;
+    use dir::{Dir, DotFilter};
     use feature::xattr;
+    use fixture::Fixture;
 
     fn is_helpful<T>(misfire: Result<T, Misfire>) -> bool {
         match misfire {
@@ -847,4 +1928,309 @@ mod test {
         let opts = Options::getopts(&[ "--level".to_string(), "69105".to_string() ]);
         assert_eq!(opts.unwrap_err(), Misfire::Useless2("level", "recurse", "tree"))
     }
+
+    #[test]
+    fn all_all_with_tree() {
+        let opts = Options::getopts(&[ "--all".to_string(), "--all".to_string(), "--tree".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("all --all", true, "tree"))
+    }
+
+    #[test]
+    fn all_all_dot_filter() {
+        let opts = Options::getopts(&[ "-aa".to_string() ]).unwrap().0;
+        assert_eq!(opts.filter.dot_filter, DotFilter::DotfilesAndDots)
+    }
+
+    #[test]
+    fn only_invalid_kind() {
+        let opts = Options::getopts(&[ "--only".to_string(), "dirs,gnomes".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::bad_argument("only", "gnomes"))
+    }
+
+    #[test]
+    fn sort_keys() {
+        use super::{SortField, SortKey};
+
+        let opts = Options::getopts(&[ "--sort=size,-modified,name".to_string() ]).unwrap();
+        assert_eq!(opts.0.filter.sort_keys.keys, vec![
+            SortKey { field: SortField::Size,             reverse: false },
+            SortKey { field: SortField::ModifiedDate,     reverse: true  },
+            SortKey { field: SortField::NameIgnoringCase, reverse: false },
+        ]);
+    }
+
+    #[test]
+    fn sort_keys_by_name_last() {
+        use super::{SortField, SortKey};
+
+        let opts = Options::getopts(&[ "--sort=ext".to_string() ]).unwrap();
+        assert_eq!(opts.0.filter.sort_keys.keys, vec![
+            SortKey { field: SortField::Extension, reverse: false },
+            SortKey { field: SortField::Name,      reverse: false },
+        ]);
+    }
+
+    #[test]
+    fn sort_name_cases() {
+        use super::{SortField, SortKey};
+
+        let opts = Options::getopts(&[ "--sort=Name".to_string() ]).unwrap();
+        assert_eq!(opts.0.filter.sort_keys.keys, vec![ SortKey { field: SortField::Name, reverse: false } ]);
+
+        let opts = Options::getopts(&[ "--sort=name-bytes".to_string() ]).unwrap();
+        assert_eq!(opts.0.filter.sort_keys.keys, vec![ SortKey { field: SortField::NameBytes, reverse: false } ]);
+    }
+
+    #[test]
+    fn versions() {
+        use super::compare_versions;
+        use std::cmp::Ordering::*;
+
+        assert_eq!(compare_versions("v1.9.3",      "v1.10.0"), Less);
+        assert_eq!(compare_versions("v2.0.0-rc1",  "v2.0.0"),  Less);
+        assert_eq!(compare_versions("v2.0.0-rc2",  "v2.0.0-rc10"),  Less);
+        assert_eq!(compare_versions("v2.0.0.tar",  "v2.0.0-rc1.tar"),  Greater);
+        assert_eq!(compare_versions("file007",     "file7"),   Less);
+        assert_eq!(compare_versions("v1.2",        "v1.2"),    Equal);
+    }
+
+    #[test]
+    fn undotted_names() {
+        use super::compare_undotted;
+        use natord;
+        use std::cmp::Ordering::*;
+
+        assert_eq!(compare_undotted(".bashrc", "bin",    natord::compare), Less);
+        assert_eq!(compare_undotted(".cargo",  "bin",    natord::compare), Greater);
+        assert_eq!(compare_undotted(".vimrc",  "vimrc",  natord::compare), Less);
+    }
+
+    #[test]
+    fn group_dotfiles_invalid() {
+        let opts = Options::getopts(&[ "--group-dotfiles=middle".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::bad_argument("group-dotfiles", "middle"))
+    }
+
+    #[test]
+    fn sort_keys_invalid() {
+        let opts = Options::getopts(&[ "--sort=size,-colour".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::bad_argument("sort", "colour"))
+    }
+
+    #[test]
+    fn total_size_without_long() {
+        let opts = Options::getopts(&[ "--total-size".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("total-size", false, "long"))
+    }
+
+    #[test]
+    fn sniff_without_long() {
+        let opts = Options::getopts(&[ "--sniff".to_string() ]);
+        assert!(opts.unwrap().0.filter.sniffs())
+    }
+
+    #[test]
+    fn total_size_invalid() {
+        let opts = Options::getopts(&[ "--long".to_string(), "--total-size=lots".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::bad_argument("total-size", "lots"))
+    }
+
+    #[test]
+    fn json_with_long() {
+        let opts = Options::getopts(&[ "--json".to_string(), "--long".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("json", "long"))
+    }
+
+    #[test]
+    fn format_without_long() {
+        let opts = Options::getopts(&[ "--format=csv".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("format", false, "long"))
+    }
+
+    #[test]
+    fn format_invalid() {
+        let opts = Options::getopts(&[ "--long".to_string(), "--format=xml".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::bad_argument("format", "xml"))
+    }
+
+    #[test]
+    fn json_with_sniff() {
+        let opts = Options::getopts(&[ "--json".to_string(), "--sniff".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("json", "sniff"))
+    }
+
+    #[test]
+    fn print0_with_long() {
+        let opts = Options::getopts(&[ "--print0".to_string(), "--long".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("print0", "long"))
+    }
+
+    #[test]
+    fn html_with_grid() {
+        let opts = Options::getopts(&[ "--html".to_string(), "--grid".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("html", "grid"))
+    }
+
+    #[test]
+    fn summary_with_json() {
+        let opts = Options::getopts(&[ "--json".to_string(), "--summary".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("json", "summary"))
+    }
+
+    #[test]
+    fn count_invalid() {
+        let opts = Options::getopts(&[ "--count=sizes".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::bad_argument("count", "sizes"))
+    }
+
+    #[test]
+    fn count_with_long() {
+        let opts = Options::getopts(&[ "--count".to_string(), "--long".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("count", "long"))
+    }
+
+    #[test]
+    fn tree_style_invalid() {
+        let opts = Options::getopts(&[ "--tree".to_string(), "--tree-style=boxes".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::bad_argument("tree-style", "boxes"))
+    }
+
+    #[test]
+    fn tree_style_without_tree() {
+        let opts = Options::getopts(&[ "--tree-style=ascii".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("tree-style", false, "tree"))
+    }
+
+    #[test]
+    fn tree_style_long_without_tree() {
+        let opts = Options::getopts(&[ "-l".to_string(), "--tree-style=ascii".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("tree-style", false, "tree"))
+    }
+
+    #[test]
+    fn markdown_with_long() {
+        let opts = Options::getopts(&[ "-lT".to_string(), "--tree-style=markdown".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("tree-style", "long"))
+    }
+
+    #[test]
+    fn absolute_and_relative() {
+        let opts = Options::getopts(&[ "--absolute".to_string(), "--relative-to=/".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("absolute", "relative-to"))
+    }
+
+    #[test]
+    fn porcelain_with_inode() {
+        let opts = Options::getopts(&[ "--porcelain".to_string(), "-i".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("porcelain", "inode"))
+    }
+
+    #[test]
+    fn porcelain_with_binary() {
+        let opts = Options::getopts(&[ "--porcelain".to_string(), "--binary".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Conflict("porcelain", "binary"))
+    }
+
+    #[test]
+    fn sizes() {
+        assert_eq!(parse_size("500"),    Some(500));
+        assert_eq!(parse_size("10M"),    Some(10_000_000));
+        assert_eq!(parse_size("1.5k"),   Some(1500));
+        assert_eq!(parse_size("2KiB"),   Some(2048));
+        assert_eq!(parse_size("7 dogs"), None);
+        assert_eq!(parse_size(""),       None);
+    }
+
+    #[test]
+    fn ages() {
+        assert_eq!(parse_age("30s", 1_000_000), Some(999_970));
+        assert_eq!(parse_age("2d",  1_000_000), Some(827_200));
+        assert_eq!(parse_age("1970-01-02", 0), Some(86_400));
+        assert_eq!(parse_age("yesterday", 0), None);
+        assert_eq!(parse_age("900000000000000000y", 0), None);
+    }
+
+    #[test]
+    fn age_too_long() {
+        let opts = Options::getopts(&[ "--newer".to_string(), "9223372036854775807m".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::FailedTimeParse("newer", "9223372036854775807m".to_string()))
+    }
+
+    #[test]
+    fn age_negative() {
+        let opts = Options::getopts(&[ "--newer=-2d".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::FailedTimeParse("newer", "-2d".to_string()))
+    }
+
+    #[test]
+    fn age_uses_accessed_flag() {
+        let (opts, _) = Options::getopts(&[ "-u".to_string(), "--newer".to_string(), "1d".to_string() ]).unwrap();
+        assert_eq!(opts.filter.age_filter.time_type, TimeType::Accessed)
+    }
+
+    #[test]
+    fn min_size_invalid() {
+        let opts = Options::getopts(&[ "--min-size".to_string(), "huge".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::FailedSizeParse("min-size", "huge".to_string()))
+    }
+
+    #[test]
+    fn time_without_long_or_age() {
+        let opts = Options::getopts(&[ "--time".to_string(), "accessed".to_string() ]);
+        assert_eq!(opts.unwrap_err(), Misfire::Useless("time", false, "long"))
+    }
+
+    #[test]
+    fn recursive_csv_has_no_headings() {
+        let opts = Options::getopts(&[ "-lR".to_string(), "--format".to_string(), "csv".to_string() ]);
+        assert!(!opts.unwrap().0.prints_headers())
+    }
+
+    #[test]
+    fn ignore_glob_invalid() {
+        let opts = Options::getopts(&[ "--ignore-glob".to_string(), "*.o|[abc".to_string() ]);
+        match opts.unwrap_err() {
+            Misfire::FailedGlobPattern(e) => assert!(e.starts_with("[abc")),
+            otherwise                     => panic!("Unexpected misfire: {:?}", otherwise),
+        }
+    }
+
+    #[test]
+    fn ignore_glob_valid() {
+        let opts = Options::getopts(&[ "-I".to_string(), "*.o|target".to_string(), "-I".to_string(), "*~".to_string() ]);
+        assert!(opts.is_ok())
+    }
+
+    /// List the names of the files in a fixture directory, made with the
+    /// given name, that are left after filtering them with the arguments.
+    fn filtered_names(name: &str, args: &[&str]) -> Vec<String> {
+        let fixture = Fixture::new(name);
+        let _ = fixture.file("main.c", b"");
+        let _ = fixture.file("main.o", b"");
+        let _ = fixture.file("notes.txt~", b"");
+        let _ = fixture.dir("target");
+        let _ = fixture.dir("targets");
+
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let opts = Options::getopts(&args).unwrap().0;
+        let dir = Dir::read_dir(fixture.path(), false).unwrap();
+        let mut files = dir.files(DotFilter::JustFiles).filter_map(Result::ok).collect();
+        opts.filter.filter_files(&mut files);
+
+        let mut names: Vec<String> = files.into_iter().map(|f| f.name).collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn ignore_glob_files() {
+        let names = filtered_names("ignore-glob-files", &[ "-I", "*.o|target", "-I", "*~" ]);
+        assert_eq!(names, vec![ "main.c", "targets" ])
+    }
+
+    #[test]
+    fn ignore_glob_whole_name() {
+        let names = filtered_names("ignore-glob-whole-name", &[ "-I", "main|*.c?" ]);
+        assert_eq!(names, vec![ "main.c", "main.o", "notes.txt~", "target", "targets" ])
+    }
 }
@@ -1,4 +1,8 @@
-use ansi_term::Style;
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Add;
+
+use ansi_term::{ANSIString, ANSIStrings, Style};
 use unicode_width::UnicodeWidthStr;
 
 use dir::Dir;
@@ -14,6 +18,7 @@ pub enum Column {
     Group,
     HardLinks,
     Inode,
+    Type,
 
     GitStatus,
 }
@@ -51,6 +56,7 @@ impl Column {
             Column::Group         => "Group",
             Column::HardLinks     => "Links",
             Column::Inode         => "inode",
+            Column::Type          => "Type",
             Column::GitStatus     => "Git",
         }
     }
@@ -73,7 +79,26 @@ impl Columns {
         self.git
     }
 
-    pub fn for_dir(&self, dir: Option<&Dir>) -> Vec<Column> {
+    /// The columns to use for the files in the given directory. Files that
+    /// have been sniffed also get a Type column.
+    pub fn for_dir(&self, dir: Option<&Dir>, sniff: bool) -> Vec<Column> {
+        let git = match dir {
+            Some(d) => self.should_scan_for_git() && d.has_git_repo(),
+            None    => false,
+        };
+
+        self.with_git(git, sniff)
+    }
+
+    /// The columns to use for every listing at once, such as when they all
+    /// get printed as one table of delimited records. These have a Git
+    /// column whenever the user asks for one, even for the files that
+    /// aren’t in a repository, so every record has the same fields.
+    pub fn for_all_dirs(&self, sniff: bool) -> Vec<Column> {
+        self.with_git(self.should_scan_for_git(), sniff)
+    }
+
+    fn with_git(&self, git: bool, sniff: bool) -> Vec<Column> {
         let mut columns = vec![];
 
         if self.inode {
@@ -110,12 +135,12 @@ impl Columns {
             columns.push(Column::Timestamp(TimeType::Accessed));
         }
 
-        if cfg!(feature="git") {
-            if let Some(d) = dir {
-                if self.should_scan_for_git() && d.has_git_repo() {
-                    columns.push(Column::GitStatus);
-                }
-            }
+        if sniff {
+            columns.push(Column::Type);
+        }
+
+        if cfg!(feature="git") && git {
+            columns.push(Column::GitStatus);
         }
 
         columns
@@ -196,36 +221,151 @@ impl Default for TimeTypes {
 }
 
 
+/// A cell of text in a table or grid, made up of spans of text that each
+/// have their own style.
+///
+/// Cells don’t get painted until they’re printed, so their widths can be
+/// worked out from the text alone, without having to skip over any ANSI
+/// escape codes. This also means they can be rendered by something other
+/// than a terminal.
 #[derive(PartialEq, Debug, Clone)]
 pub struct Cell {
-    pub length: usize,
+    pub spans: Vec<Span>,
+}
+
+/// One span of text in a cell, which all gets painted in the same style.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Span {
+    pub style: Style,
+
+    /// The name of the colour that the style is, such as `perms-user_read`,
+    /// which becomes the span’s CSS class when it’s rendered as HTML. Text
+    /// that isn’t given a colour, such as padding, doesn’t have one.
+    pub class: Option<Cow<'static, str>>,
+
     pub text: String,
 }
 
 impl Cell {
     pub fn empty() -> Cell {
         Cell {
-            text: String::new(),
-            length: 0,
+            spans: Vec::new(),
         }
     }
 
+    /// A cell of text in the given style, which isn’t one of the colours.
     pub fn paint(style: Style, string: &str) -> Cell {
         Cell {
-            text: style.paint(string).to_string(),
-            length: UnicodeWidthStr::width(string),
+            spans: vec![ Span { style: style, class: None, text: string.to_owned() } ],
+        }
+    }
+
+    /// A cell of text painted with one of the colours, along with the name
+    /// of that colour.
+    pub fn coloured<C: Into<Cow<'static, str>>>(style: Style, class: C, string: &str) -> Cell {
+        Cell {
+            spans: vec![ Span { style: style, class: Some(class.into()), text: string.to_owned() } ],
+        }
+    }
+
+    /// The number of columns this cell takes up on screen.
+    pub fn width(&self) -> usize {
+        self.spans.iter().map(|span| UnicodeWidthStr::width(&*span.text)).fold(0, Add::add)
+    }
+
+    /// The text of this cell, without any styles.
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+
+        for span in &self.spans {
+            text.push_str(&span.text);
         }
+
+        text
     }
 
     pub fn add_spaces(&mut self, count: usize) {
-        self.length += count;
+        if count == 0 {
+            return;
+        }
+
+        let mut spaces = String::new();
         for _ in 0 .. count {
-            self.text.push(' ');
+            spaces.push(' ');
         }
+
+        self.spans.push(Span { style: Style::default(), class: None, text: spaces });
     }
 
     pub fn append(&mut self, other: &Cell) {
-        self.length += other.length;
-        self.text.push_str(&*other.text);
+        self.spans.extend(other.spans.iter().cloned());
+    }
+
+    /// Pad this cell with spaces on the side given by the alignment, so it
+    /// takes up at least the given width.
+    pub fn pad(&mut self, width: usize, alignment: Alignment) {
+        let current = self.width();
+        if current >= width {
+            return;
+        }
+
+        match alignment {
+            Alignment::Left  => self.add_spaces(width - current),
+            Alignment::Right => {
+                let mut padded = Cell::empty();
+                padded.add_spaces(width - current);
+                padded.append(self);
+                *self = padded;
+            },
+        }
+    }
+}
+
+/// Cells get painted with ANSI escape codes when they’re displayed.
+impl fmt::Display for Cell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let strings: Vec<ANSIString> = self.spans.iter().map(|span| span.style.paint(&*span.text)).collect();
+        write!(f, "{}", ANSIStrings(&strings))
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{Alignment, Cell, Column, Columns};
+    use ansi_term::Colour::{Blue, Green};
+
+    #[test]
+    fn git_for_all_dirs() {
+        let columns = Columns { git: true, .. Columns::default() };
+        assert_eq!(columns.for_dir(None, false).contains(&Column::GitStatus), false);
+        assert_eq!(columns.for_all_dirs(false).contains(&Column::GitStatus), cfg!(feature="git"));
+    }
+
+    #[test]
+    fn width() {
+        let mut cell = Cell::paint(Green.bold(), "1.2");
+        cell.append(&Cell::paint(Green.normal(), "k"));
+        assert_eq!(cell.width(), 4)
+    }
+
+    #[test]
+    fn wide_characters() {
+        let cell = Cell::paint(Blue.bold(), "日本");
+        assert_eq!(cell.width(), 4)
+    }
+
+    #[test]
+    fn pad_left() {
+        let mut cell = Cell::paint(Blue.bold(), "src");
+        cell.pad(5, Alignment::Left);
+        assert_eq!(cell.text(), "src  ")
+    }
+
+    #[test]
+    fn pad_right() {
+        let mut cell = Cell::paint(Green.bold(), "42");
+        cell.pad(5, Alignment::Right);
+        assert_eq!(cell.text(), "   42")
     }
 }
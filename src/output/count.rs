@@ -0,0 +1,122 @@
+//! The **count** view doesn’t list any files. Instead, it prints how many
+//! files of each kind there are in each directory, and, optionally, how
+//! many there are in each of the categories that decide a file’s colour,
+//! such as images, documents, or compressed files:
+//!
+//! ```text
+//! Kind      Count
+//! directory     3
+//! file         14
+//!
+//! Category  Count
+//! directory     3
+//! image        11
+//! normal        3
+//! ```
+//!
+//! Kinds and categories that no files belong to get left out. The counts
+//! can also be printed as JSON, with one object per directory that always
+//! contains every kind and category.
+
+use std::collections::BTreeMap;
+
+use ansi_term::Style;
+use locale;
+use rustc_serialize::json::{Json as JsonValue, ToJson};
+
+use colours::Colours;
+use dir::Dir;
+use file::File;
+use filetype::Category;
+use output::column::{Alignment, Cell};
+use output::{kind, KINDS};
+
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Count {
+
+    /// Whether to count the files in each category, as well as of each kind.
+    pub categories: bool,
+
+    /// Whether to print the counts as JSON, rather than as a table.
+    pub json: bool,
+
+    /// The colours to use to display the tables, along with the categories
+    /// to count the files in.
+    pub colours: Colours,
+}
+
+impl Count {
+    pub fn view(&self, dir: Option<&Dir>, files: &[File]) {
+        let kinds: Vec<(&str, u64)> = KINDS.iter().map(|&k| {
+            (k, files.iter().filter(|f| kind(f) == k).count() as u64)
+        }).collect();
+
+        let classifier = &self.colours.classifier;
+        let categories: Vec<(Category, u64)> = if self.categories {
+            classifier.categories().into_iter().map(|c| {
+                (c, files.iter().filter(|f| classifier.category(f) == c).count() as u64)
+            }).collect()
+        }
+        else {
+            Vec::new()
+        };
+
+        if self.json {
+            let mut object = BTreeMap::new();
+            let path = dir.map(|d| d.path.to_string_lossy().into_owned());
+            let _ = object.insert("path".to_owned(), path.to_json());
+            let _ = object.insert("kinds".to_owned(), counts_to_json(kinds.into_iter()));
+
+            if self.categories {
+                let categories = categories.into_iter().map(|(c, count)| (c.name(), count));
+                let _ = object.insert("categories".to_owned(), counts_to_json(categories));
+            }
+
+            println!("{}", JsonValue::Object(object));
+        }
+        else {
+            let kinds: Vec<(Cell, u64)> = kinds.into_iter().map(|(k, count)| (Cell::paint(Style::default(), k), count)).collect();
+            self.print_table("Kind", kinds);
+
+            if self.categories {
+                let categories = categories.into_iter().map(|(c, count)| (Cell::paint(c.colour(&self.colours), c.name()), count)).collect();
+                print!("\n");
+                self.print_table("Category", categories);
+            }
+        }
+    }
+
+    /// Print a table with the given title, and one row for each name that
+    /// has a count above zero.
+    fn print_table(&self, title: &str, counts: Vec<(Cell, u64)>) {
+        let numeric = locale::Numeric::load_user_locale().unwrap_or_else(|_| locale::Numeric::english());
+
+        let mut rows = vec![ (Cell::coloured(self.colours.header, "header", title), Cell::coloured(self.colours.header, "header", "Count")) ];
+        for (name, count) in counts {
+            if count > 0 {
+                rows.push((name, Cell::coloured(self.colours.size.numbers, "size-numbers", &numeric.format_int(count))));
+            }
+        }
+
+        let name_width = rows.iter().map(|&(ref name, _)| name.width()).max().unwrap_or(0);
+        let count_width = rows.iter().map(|&(_, ref count)| count.width()).max().unwrap_or(0);
+
+        for (mut name, mut count) in rows {
+            name.pad(name_width, Alignment::Left);
+            count.pad(count_width, Alignment::Right);
+            name.add_spaces(1);
+            name.append(&count);
+            println!("{}", name);
+        }
+    }
+}
+
+fn counts_to_json<'a, I>(counts: I) -> JsonValue
+where I: Iterator<Item=(&'a str, u64)> {
+    let mut object = BTreeMap::new();
+    for (name, count) in counts {
+        let _ = object.insert(name.to_owned(), count.to_json());
+    }
+    JsonValue::Object(object)
+}
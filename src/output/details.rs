@@ -111,6 +111,7 @@
 //! are used in place of the filename.
 
 
+use std::collections::HashMap;
 use std::error::Error;
 use std::io;
 use std::path::PathBuf;
@@ -123,10 +124,12 @@ use dir::Dir;
 use feature::xattr::{Attribute, FileAttributes};
 use file::fields as f;
 use file::File;
+use filetype::{file_class, file_colour};
 use options::{FileFilter, RecurseOptions};
-use output::column::{Alignment, Column, Columns, Cell, SizeFormat};
+use output::column::{Column, Columns, Cell, Span, SizeFormat};
+use output::summary::Summary;
 
-use ansi_term::{ANSIString, ANSIStrings, Style};
+use ansi_term::Style;
 
 use datetime::local::{LocalDateTime, DatePiece};
 use datetime::format::DateFormat;
@@ -134,10 +137,12 @@ use datetime::zoned::TimeZone;
 
 use locale;
 
+use scoped_threadpool::Pool;
+
 use users::{OSUsers, Users};
 use users::mock::MockUsers;
 
-use super::filename;
+use super::{filename, html, iso_8601, PathFormat};
 
 
 /// With the **Details** view, the output gets formatted into columns, with
@@ -151,7 +156,7 @@ use super::filename;
 ///
 /// Almost all the heavy lifting is done in a Table object, which handles the
 /// columns for each row.
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct Details {
 
     /// A Columns object that says which columns should be included in the
@@ -173,45 +178,196 @@ pub struct Details {
     /// Whether to show each file's extended attributes.
     pub xattr: bool,
 
+    /// Whether to calculate the total size of each directory's contents,
+    /// and if so, how to measure it.
+    pub total_size: Option<TotalSize>,
+
+    /// How to display the name of each file.
+    pub path_format: PathFormat,
+
+    /// Whether to use the porcelain format, which renders every cell the
+    /// same way regardless of the user’s locale or the current date.
+    pub porcelain: bool,
+
+    /// Whether to print the table with padded columns, or as records of
+    /// delimiter-separated values.
+    pub format: TableFormat,
+
+    /// Whether to print the table as HTML, rather than for a terminal.
+    pub html: bool,
+
+    /// Which characters to draw the tree with, for files in the tree view
+    /// as well as their extended attributes and errors.
+    pub tree_style: TreeStyle,
+
     /// The colours to use to display information in the table, including the
     /// colour of the tree view symbols.
     pub colours: Colours,
 }
 
+/// How to measure the total size of a directory's contents.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum TotalSize {
+
+    /// Add up the lengths of the files, which is how big they would be if
+    /// they were copied somewhere else.
+    Apparent,
+
+    /// Add up the blocks used by the files, which is how much space they
+    /// actually take up on disk.
+    OnDisk,
+}
+
+/// How to write out the rows of the table.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum TableFormat {
+
+    /// Pad each column to the width of its widest cell, and draw the tree
+    /// next to the file names.
+    Padded,
+
+    /// Write each file as a record of unpadded fields separated by the
+    /// given character, after a record of column headers, so the output
+    /// can be read into a spreadsheet.
+    Delimited(char),
+}
+
+/// The characters to draw the tree view with.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub enum TreeStyle {
+
+    /// Unicode box-drawing characters, such as `├──`.
+    Unicode,
+
+    /// Plain ASCII characters, such as `|--`, for terminals and logs that
+    /// can’t display Unicode.
+    Ascii,
+
+    /// Markdown bullets, indented by two spaces at each level, so the tree
+    /// becomes a nested list.
+    Markdown,
+}
+
+impl Default for TreeStyle {
+    fn default() -> TreeStyle {
+        TreeStyle::Unicode
+    }
+}
+
 impl Details {
 
     /// Print the details of the given vector of files -- all of which will
-    /// have been read from the given directory, if present -- to stdout.
-    pub fn view(&self, dir: Option<&Dir>, files: Vec<File>) {
+    /// have been read from the given directory, if present -- to stdout,
+    /// along with the files inside them in tree mode. Returns the totals of
+    /// all the files that were shown. Delimited output only gets a header
+    /// row for the first listing, so every listing has the same columns.
+    pub fn view(&self, dir: Option<&Dir>, mut files: Vec<File>, first_listing: bool) -> Summary {
+
+        // The files in a directory have already been sorted, but the ones
+        // given on the command line haven’t, as the other views list them
+        // in the order they were given in.
+        if dir.is_none() {
+            self.filter.sort_files(&mut files);
+        }
 
         // First, transform the Columns object into a vector of columns for
         // the current directory.
         let columns_for_dir = match self.columns {
-            Some(cols) => cols.for_dir(dir),
+            Some(cols) if self.format != TableFormat::Padded => cols.for_all_dirs(self.filter.sniffs()),
+            Some(cols) => cols.for_dir(dir, self.filter.sniffs()),
             None => Vec::new(),
         };
 
-        // Next, add a header if the user requests it.
-        let mut table = Table::with_options(self.colours, columns_for_dir);
-        if self.header { table.add_header() }
+        // Next, add a header if the user requests it. Delimited output
+        // always gets one, which it writes by itself.
+        let mut table = Table::with_options(self.colours.clone(), columns_for_dir, self.porcelain);
+        if self.header && self.format == TableFormat::Padded { table.add_header() }
 
         // Then add files to the table and print it out.
-        self.add_files_to_table(&mut table, files, 0);
-        for cell in table.print_table() {
-            println!("{}", cell.text);
+        let mut summary = Summary::default();
+        self.add_files_to_table(&mut table, files, 0, &mut summary, &mut HashMap::new());
+
+        match self.format {
+            TableFormat::Padded => {
+                for cell in table.print_table(self.tree_style) {
+                    if self.html { println!("{}", html::render(&cell)) }
+                            else { println!("{}", cell) }
+                }
+            },
+            TableFormat::Delimited(delimiter) => {
+                let tree = self.recurse.map_or(false, |r| r.tree);
+
+                // Recursing doesn’t print the directories’ names, so each
+                // record says which one it came from instead.
+                let directory = match self.recurse {
+                    Some(r) if !r.tree => Some(dir.map_or(String::new(), |d| d.path.display().to_string())),
+                    _                  => None,
+                };
+
+                for line in table.print_delimited(delimiter, tree, directory.as_ref().map(|d| &**d), first_listing) {
+                    println!("{}", line);
+                }
+            },
+        }
+
+        summary
+    }
+
+    /// Calculates the total size of each directory in the given vector of
+    /// files, if the user asked for them, and re-sorts the files if their
+    /// new sizes could change the order. Each directory's subtree is
+    /// walked using the given pool of threads.
+    ///
+    /// The sizes of the directories inside them get stored in the given
+    /// map, so when a tree gets to those, their sizes are already known,
+    /// rather than their subtrees getting walked again at every depth.
+    pub fn add_total_sizes(&self, pool: &mut Pool, files: &mut Vec<File>, subtrees: &mut HashMap<PathBuf, u64>) {
+        use std::sync::Mutex;
+
+        let on_disk = match self.total_size {
+            Some(TotalSize::Apparent)  => false,
+            Some(TotalSize::OnDisk)    => true,
+            None                       => return,
+        };
+
+        let found = Mutex::new(HashMap::new());
+
+        pool.scoped(|scoped| {
+            for file in files.iter_mut().filter(|f| f.is_directory() && !f.is_all_all) {
+                if let Some(size) = subtrees.remove(&file.path) {
+                    file.total_size = Some(size);
+                    continue;
+                }
+
+                let found = &found;
+                scoped.execute(move || {
+                    let mut sizes = HashMap::new();
+                    file.total_size = Some(file.calculate_total_size(on_disk, &mut sizes));
+                    found.lock().unwrap().extend(sizes);
+                });
+            }
+        });
+
+        subtrees.extend(found.into_inner().unwrap());
+
+        if self.filter.sorts_by_size() {
+            self.filter.sort_files(files);
         }
     }
 
     /// Adds files to the table, possibly recursively. This is easily
-    /// parallelisable, and uses a pool of threads.
-    fn add_files_to_table<'dir, U: Users+Send>(&self, mut table: &mut Table<U>, src: Vec<File<'dir>>, depth: usize) {
+    /// parallelisable, and uses a pool of threads. Every file that gets
+    /// shown is counted up in the given summary, and the total sizes of
+    /// directories further down the tree are kept in the given map.
+    fn add_files_to_table<'dir, U: Users+Send>(&self, mut table: &mut Table<U>, mut src: Vec<File<'dir>>, depth: usize, summary: &mut Summary, subtrees: &mut HashMap<PathBuf, u64>) {
         use num_cpus;
-        use scoped_threadpool::Pool;
         use std::sync::{Arc, Mutex};
 
         let mut pool = Pool::new(num_cpus::get() as u32);
         let mut file_eggs = Vec::new();
 
+        self.add_total_sizes(&mut pool, &mut src, subtrees);
+
         struct Egg<'_> {
             cells:   Vec<Cell>,
             name:    Cell,
@@ -219,13 +375,14 @@ impl Details {
             errors:  Vec<(io::Error, Option<PathBuf>)>,
             dir:     Option<Dir>,
             file:    Arc<File<'_>>,
+            index:   usize,
         }
 
         pool.scoped(|scoped| {
             let file_eggs = Arc::new(Mutex::new(&mut file_eggs));
             let table = Arc::new(Mutex::new(&mut table));
 
-            for file in src.into_iter() {
+            for (index, file) in src.into_iter().enumerate() {
                 let file: Arc<File> = Arc::new(file);
                 let file_eggs = file_eggs.clone();
                 let table = table.clone();
@@ -251,16 +408,13 @@ impl Details {
 
                     let cells = table.lock().unwrap().cells_for_file(&file, !xattrs.is_empty());
 
-                    let name = Cell {
-                        text: filename(&file, &self.colours, true),
-                        length: file.file_name_width()
-                    };
+                    let name = filename(&file, &self.colours, self.format == TableFormat::Padded, &self.path_format);
 
                     let mut dir = None;
 
                     if let Some(r) = self.recurse {
                         if file.is_directory() && r.tree && !r.is_too_deep(depth) {
-                            if let Ok(d) = file.to_dir(false) {
+                            if let Ok(d) = file.to_dir(self.filter.should_scan_for_git()) {
                                 dir = Some(d);
                             }
                         }
@@ -273,6 +427,7 @@ impl Details {
                         errors: errors,
                         dir: dir,
                         file: file,
+                        index: index,
                     };
 
                     file_eggs.lock().unwrap().push(egg);
@@ -280,24 +435,39 @@ impl Details {
             }
         });
 
-        file_eggs.sort_by(|a, b| self.filter.compare_files(&*a.file, &*b.file));
+        // The eggs get hatched in parallel, so put them back in the order
+        // that the files were given in.
+        file_eggs.sort_by(|a, b| a.index.cmp(&b.index));
+
+        // The index of the row for the last file that actually gets added at
+        // this depth. This can't be worked out from the number of eggs, as
+        // some of them may turn out to be hidden.
+        let mut last_row = None;
 
-        let num_eggs = file_eggs.len();
-        for (index, egg) in file_eggs.into_iter().enumerate() {
+        for egg in file_eggs.into_iter() {
             let mut files = Vec::new();
             let mut errors = egg.errors;
 
+            // Files given on the command line always get displayed; any
+            // other file that doesn't match the filter is hidden, unless
+            // it's a directory with matching files somewhere inside it.
+            let hidden = egg.file.dir.is_some() && !self.filter.matches(&*egg.file);
+            if hidden && egg.dir.is_none() {
+                continue;
+            }
+
             let row = Row {
                 depth:    depth,
                 cells:    Some(egg.cells),
                 name:     egg.name,
-                last:     index == num_eggs - 1,
+                last:     false,
             };
 
+            let row_index = table.rows.len();
             table.rows.push(row);
 
             if let Some(ref dir) = egg.dir {
-                for file_to_add in dir.files() {
+                for file_to_add in dir.files(self.filter.dot_filter) {
                     match file_to_add {
                         Ok(f)          => files.push(f),
                         Err((path, e)) => errors.push((e, Some(path)))
@@ -305,6 +475,8 @@ impl Details {
                 }
 
                 self.filter.filter_files(&mut files);
+                self.filter.sort_files(&mut files);
+                self.filter.sniff_files(&mut files);
 
                 if !files.is_empty() {
                     for xattr in egg.xattrs {
@@ -315,11 +487,29 @@ impl Details {
                         table.add_error(&error, depth + 1, false, path);
                     }
 
-                    self.add_files_to_table(table, files, depth + 1);
+                    let rows_before = table.rows.len();
+                    self.add_files_to_table(table, files, depth + 1, summary, subtrees);
+
+                    if hidden && table.rows.len() == rows_before {
+                        table.rows.truncate(row_index);
+                    }
+                    else {
+                        last_row = Some(row_index);
+                        summary.add_file(&egg.file);
+                    }
+
                     continue;
                 }
             }
 
+            if hidden {
+                table.rows.truncate(row_index);
+                continue;
+            }
+
+            last_row = Some(row_index);
+            summary.add_file(&egg.file);
+
             let count = egg.xattrs.len();
             for (index, xattr) in egg.xattrs.into_iter().enumerate() {
                 table.add_xattr(xattr, depth + 1, errors.is_empty() && index == count - 1);
@@ -330,6 +520,10 @@ impl Details {
                 table.add_error(&error, depth + 1, index == count - 1, path);
             }
         }
+
+        if let Some(index) = last_row {
+            table.rows[index].last = true;
+        }
     }
 }
 
@@ -366,7 +560,7 @@ impl Row {
     /// not, returns 0.
     fn column_width(&self, index: usize) -> usize {
         match self.cells {
-            Some(ref cells) => cells[index].length,
+            Some(ref cells) => cells[index].width(),
             None => 0,
         }
     }
@@ -385,12 +579,13 @@ pub struct Table<U> {
     users:        U,
     colours:      Colours,
     current_year: i64,
+    porcelain:    bool,
 }
 
 impl Default for Table<MockUsers> {
     fn default() -> Table<MockUsers> {
         Table {
-            columns: Columns::default().for_dir(None),
+            columns: Columns::default().for_dir(None, false),
             rows:    Vec::new(),
             time:    locale::Time::english(),
             numeric: locale::Numeric::english(),
@@ -398,6 +593,7 @@ impl Default for Table<MockUsers> {
             users:   MockUsers::with_current_uid(0),
             colours: Colours::default(),
             current_year: 1234,
+            porcelain: false,
         }
     }
 }
@@ -406,17 +602,30 @@ impl Table<OSUsers> {
 
     /// Create a new, empty Table object, setting the caching fields to their
     /// empty states.
-    pub fn with_options(colours: Colours, columns: Vec<Column>) -> Table<OSUsers> {
+    ///
+    /// A table in the porcelain format ignores the user’s locale, so it has
+    /// no thousands separators in its numbers, and it prints full timestamps
+    /// and unpadded columns, so its output only changes when the files do.
+    pub fn with_options(colours: Colours, columns: Vec<Column>, porcelain: bool) -> Table<OSUsers> {
+        let (time, numeric) = if porcelain {
+            (locale::Time::english(), locale::Numeric::new(".", ""))
+        }
+        else {
+            (locale::Time::load_user_locale().unwrap_or_else(|_| locale::Time::english()),
+             locale::Numeric::load_user_locale().unwrap_or_else(|_| locale::Numeric::english()))
+        };
+
         Table {
             columns: columns,
             rows:    Vec::new(),
 
-            time:         locale::Time::load_user_locale().unwrap_or_else(|_| locale::Time::english()),
-            numeric:      locale::Numeric::load_user_locale().unwrap_or_else(|_| locale::Numeric::english()),
+            time:         time,
+            numeric:      numeric,
             tz:           TimeZone::localtime().unwrap(),
             users:        OSUsers::empty_cache(),
             colours:      colours,
             current_year: LocalDateTime::now().year(),
+            porcelain:    porcelain,
         }
     }
 }
@@ -429,8 +638,8 @@ impl<U> Table<U> where U: Users {
     pub fn add_header(&mut self) {
         let row = Row {
             depth:    0,
-            cells:    Some(self.columns.iter().map(|c| Cell::paint(self.colours.header, c.header())).collect()),
-            name:     Cell::paint(self.colours.header, "Name"),
+            cells:    Some(self.columns.iter().map(|c| Cell::coloured(self.colours.header, "header", c.header())).collect()),
+            name:     Cell::coloured(self.colours.header, "header", "Name"),
             last:     false,
         };
 
@@ -446,7 +655,7 @@ impl<U> Table<U> where U: Users {
         let row = Row {
             depth:    depth,
             cells:    None,
-            name:     Cell::paint(self.colours.broken_arrow, &error_message),
+            name:     Cell::coloured(self.colours.broken_arrow, "broken_arrow", &error_message),
             last:     last,
         };
 
@@ -457,18 +666,18 @@ impl<U> Table<U> where U: Users {
         let row = Row {
             depth:    depth,
             cells:    None,
-            name:     Cell::paint(self.colours.perms.attribute, &format!("{} (len {})", xattr.name, xattr.size)),
+            name:     Cell::coloured(self.colours.perms.attribute, "perms-attribute", &format!("{} (len {})", xattr.name, xattr.size)),
             last:     last,
         };
 
         self.rows.push(row);
     }
 
-    pub fn add_file_with_cells(&mut self, cells: Vec<Cell>, file: &File, depth: usize, last: bool, links: bool) {
+    pub fn add_file_with_cells(&mut self, cells: Vec<Cell>, name: Cell, depth: usize, last: bool) {
         let row = Row {
             depth:    depth,
             cells:    Some(cells),
-            name:     Cell { text: filename(file, &self.colours, links), length: file.file_name_width() },
+            name:     name,
             last:     last,
         };
 
@@ -488,13 +697,14 @@ impl<U> Table<U> where U: Users {
 
         match *column {
             Column::Permissions          => self.render_permissions(file.permissions(), xattrs),
-            Column::FileSize(fmt)        => self.render_size(file.size(), fmt),
+            Column::FileSize(fmt)        => render_size(file.size(), fmt, &self.numeric, &self.colours),
             Column::Timestamp(Modified)  => self.render_time(file.modified_time()),
             Column::Timestamp(Created)   => self.render_time(file.created_time()),
             Column::Timestamp(Accessed)  => self.render_time(file.accessed_time()),
             Column::HardLinks            => self.render_links(file.links()),
             Column::Inode                => self.render_inode(file.inode()),
             Column::Blocks               => self.render_blocks(file.blocks()),
+            Column::Type                 => self.render_type(file),
             Column::User                 => self.render_user(file.user()),
             Column::Group                => self.render_group(file.group()),
             Column::GitStatus            => self.render_git_status(file.git_status()),
@@ -503,92 +713,78 @@ impl<U> Table<U> where U: Users {
 
     fn render_permissions(&self, permissions: f::Permissions, xattrs: bool) -> Cell {
         let c = self.colours.perms;
-        let bit = |bit, chr: &'static str, style: Style| {
-            if bit { style.paint(chr) } else { self.colours.punctuation.paint("-") }
+        let bit = |bit, chr: &'static str, style: Style, class: &'static str| {
+            if bit { Cell::coloured(style, class, chr) } else { Cell::coloured(self.colours.punctuation, "punctuation", "-") }
         };
 
         let file_type = match permissions.file_type {
-            f::Type::File       => self.colours.filetypes.normal.paint("."),
-            f::Type::Directory  => self.colours.filetypes.directory.paint("d"),
-            f::Type::Pipe       => self.colours.filetypes.special.paint("|"),
-            f::Type::Link       => self.colours.filetypes.symlink.paint("l"),
-            f::Type::Special    => self.colours.filetypes.special.paint("?"),
+            f::Type::File       => Cell::coloured(self.colours.filetypes.normal, "filetypes-normal", "."),
+            f::Type::Directory  => Cell::coloured(self.colours.filetypes.directory, "filetypes-directory", "d"),
+            f::Type::Pipe       => Cell::coloured(self.colours.filetypes.special, "filetypes-special", "|"),
+            f::Type::Link       => Cell::coloured(self.colours.filetypes.symlink, "filetypes-symlink", "l"),
+            f::Type::Special    => Cell::coloured(self.colours.filetypes.special, "filetypes-special", "?"),
         };
 
-        let x_colour = if let f::Type::File = permissions.file_type { c.user_execute_file }
-                                                               else { c.user_execute_other };
-
-        let mut columns = vec![
-            file_type,
-            bit(permissions.user_read,     "r", c.user_read),
-            bit(permissions.user_write,    "w", c.user_write),
-            bit(permissions.user_execute,  "x", x_colour),
-            bit(permissions.group_read,    "r", c.group_read),
-            bit(permissions.group_write,   "w", c.group_write),
-            bit(permissions.group_execute, "x", c.group_execute),
-            bit(permissions.other_read,    "r", c.other_read),
-            bit(permissions.other_write,   "w", c.other_write),
-            bit(permissions.other_execute, "x", c.other_execute),
+        let (x_colour, x_class) = if let f::Type::File = permissions.file_type { (c.user_execute_file, "perms-user_execute_file") }
+                                                                          else { (c.user_execute_other, "perms-user_execute_other") };
+
+        let bits = vec![
+            bit(permissions.user_read,     "r", c.user_read,      "perms-user_read"),
+            bit(permissions.user_write,    "w", c.user_write,     "perms-user_write"),
+            bit(permissions.user_execute,  "x", x_colour,         x_class),
+            bit(permissions.group_read,    "r", c.group_read,     "perms-group_read"),
+            bit(permissions.group_write,   "w", c.group_write,    "perms-group_write"),
+            bit(permissions.group_execute, "x", c.group_execute,  "perms-group_execute"),
+            bit(permissions.other_read,    "r", c.other_read,     "perms-other_read"),
+            bit(permissions.other_write,   "w", c.other_write,    "perms-other_write"),
+            bit(permissions.other_execute, "x", c.other_execute,  "perms-other_execute"),
         ];
 
-        if xattrs {
-            columns.push(c.attribute.paint("@"));
+        let mut cell = file_type;
+        for bit in &bits {
+            cell.append(bit);
         }
 
-        Cell {
-            text: ANSIStrings(&columns).to_string(),
-            length: columns.len(),
+        if xattrs {
+            cell.append(&Cell::coloured(c.attribute, "perms-attribute", "@"));
         }
+
+        cell
     }
 
     fn render_links(&self, links: f::Links) -> Cell {
-        let style = if links.multiple { self.colours.links.multi_link_file }
-                                 else { self.colours.links.normal };
+        let (style, class) = if links.multiple { (self.colours.links.multi_link_file, "links-multi_link_file") }
+                                          else { (self.colours.links.normal, "links-normal") };
 
-        Cell::paint(style, &self.numeric.format_int(links.count))
+        Cell::coloured(style, class, &self.numeric.format_int(links.count))
     }
 
     fn render_blocks(&self, blocks: f::Blocks) -> Cell {
         match blocks {
-            f::Blocks::Some(blocks)  => Cell::paint(self.colours.blocks, &blocks.to_string()),
-            f::Blocks::None          => Cell::paint(self.colours.punctuation, "-"),
+            f::Blocks::Some(blocks)  => Cell::coloured(self.colours.blocks, "blocks", &blocks.to_string()),
+            f::Blocks::None          => Cell::coloured(self.colours.punctuation, "punctuation", "-"),
         }
     }
 
-    fn render_inode(&self, inode: f::Inode) -> Cell {
-        Cell::paint(self.colours.inode, &inode.0.to_string())
+    /// The type of a file, worked out from its contents, in the same colour
+    /// as its name.
+    fn render_type(&self, file: &File) -> Cell {
+        match file.magic {
+            Some(magic)  => Cell::coloured(file_colour(&self.colours, file), file_class(&self.colours, file), magic.name()),
+            None         => Cell::coloured(self.colours.punctuation, "punctuation", "-"),
+        }
     }
 
-    fn render_size(&self, size: f::Size, size_format: SizeFormat) -> Cell {
-        use number_prefix::{binary_prefix, decimal_prefix, Prefixed, Standalone, PrefixNames};
-
-        if let f::Size::Some(offset) = size {
-            let result = match size_format {
-                SizeFormat::DecimalBytes  => decimal_prefix(offset as f64),
-                SizeFormat::BinaryBytes   => binary_prefix(offset as f64),
-                SizeFormat::JustBytes     => return Cell::paint(self.colours.size.numbers, &self.numeric.format_int(offset)),
-            };
-
-            match result {
-                Standalone(bytes)    => Cell::paint(self.colours.size.numbers, &*bytes.to_string()),
-                Prefixed(prefix, n)  => {
-                    let number = if n < 10f64 { self.numeric.format_float(n, 1) } else { self.numeric.format_int(n as isize) };
-                    let symbol = prefix.symbol();
-
-                    Cell {
-                        text: ANSIStrings( &[ self.colours.size.numbers.paint(&number[..]), self.colours.size.unit.paint(symbol) ]).to_string(),
-                        length: number.len() + symbol.len(),
-                    }
-                }
-            }
-        }
-        else {
-            Cell::paint(self.colours.punctuation, "-")
-        }
+    fn render_inode(&self, inode: f::Inode) -> Cell {
+        Cell::coloured(self.colours.inode, "inode", &inode.0.to_string())
     }
 
     #[allow(trivial_numeric_casts)]
     fn render_time(&self, timestamp: f::Time) -> Cell {
+        if self.porcelain {
+            return Cell::coloured(self.colours.date, "date", &iso_8601(timestamp));
+        }
+
         let date = self.tz.at(LocalDateTime::at(timestamp.0 as i64));
 
         let datestamp = if date.year() == self.current_year {
@@ -598,25 +794,23 @@ impl<U> Table<U> where U: Users {
                 DATE_AND_YEAR.format(&date, &self.time)
             };
 
-        Cell::paint(self.colours.date, &datestamp)
+        Cell::coloured(self.colours.date, "date", &datestamp)
     }
 
     fn render_git_status(&self, git: f::Git) -> Cell {
-        Cell {
-            text: ANSIStrings(&[ self.render_git_char(git.staged),
-                                 self.render_git_char(git.unstaged) ]).to_string(),
-            length: 2,
-        }
+        let mut cell = self.render_git_char(git.staged);
+        cell.append(&self.render_git_char(git.unstaged));
+        cell
     }
 
-    fn render_git_char(&self, status: f::GitStatus) -> ANSIString {
+    fn render_git_char(&self, status: f::GitStatus) -> Cell {
         match status {
-            f::GitStatus::NotModified  => self.colours.punctuation.paint("-"),
-            f::GitStatus::New          => self.colours.git.new.paint("N"),
-            f::GitStatus::Modified     => self.colours.git.modified.paint("M"),
-            f::GitStatus::Deleted      => self.colours.git.deleted.paint("D"),
-            f::GitStatus::Renamed      => self.colours.git.renamed.paint("R"),
-            f::GitStatus::TypeChange   => self.colours.git.typechange.paint("T"),
+            f::GitStatus::NotModified  => Cell::coloured(self.colours.punctuation, "punctuation", "-"),
+            f::GitStatus::New          => Cell::coloured(self.colours.git.new, "git-new", "N"),
+            f::GitStatus::Modified     => Cell::coloured(self.colours.git.modified, "git-modified", "M"),
+            f::GitStatus::Deleted      => Cell::coloured(self.colours.git.deleted, "git-deleted", "D"),
+            f::GitStatus::Renamed      => Cell::coloured(self.colours.git.renamed, "git-renamed", "R"),
+            f::GitStatus::TypeChange   => Cell::coloured(self.colours.git.typechange, "git-typechange", "T"),
         }
     }
 
@@ -626,13 +820,14 @@ impl<U> Table<U> where U: Users {
             None        => user.0.to_string(),
         };
 
-        let style = if self.users.get_current_uid() == user.0 { self.colours.users.user_you }
-                                                         else { self.colours.users.user_someone_else };
-        Cell::paint(style, &*user_name)
+        let (style, class) = if self.users.get_current_uid() == user.0 { (self.colours.users.user_you, "users-user_you") }
+                                                                  else { (self.colours.users.user_someone_else, "users-user_someone_else") };
+        Cell::coloured(style, class, &*user_name)
     }
 
     fn render_group(&mut self, group: f::Group) -> Cell {
         let mut style = self.colours.users.group_not_yours;
+        let mut class = "users-group_not_yours";
 
         let group_name = match self.users.get_group_by_gid(group.0) {
             Some(group) => {
@@ -640,6 +835,7 @@ impl<U> Table<U> where U: Users {
                 if let Some(current_user) = self.users.get_user_by_uid(current_uid) {
                     if current_user.primary_group == group.gid || group.members.contains(&current_user.name) {
                         style = self.colours.users.group_yours;
+                        class = "users-group_yours";
                     }
                 }
                 group.name
@@ -647,11 +843,45 @@ impl<U> Table<U> where U: Users {
             None => group.0.to_string(),
         };
 
-        Cell::paint(style, &*group_name)
+        Cell::coloured(style, class, &*group_name)
+    }
+
+    /// Render the table as a vector of delimiter-separated records, starting
+    /// with the names of the columns if `header` is set, to be displayed on
+    /// standard output.
+    /// If the table is a tree, each file’s depth goes in an extra column,
+    /// and if a directory is given, its path goes in another after that.
+    ///
+    /// Only files get records: the rows for errors and extended attributes
+    /// have no fields to put in them, so they get left out.
+    pub fn print_delimited(&self, delimiter: char, tree: bool, directory: Option<&str>, header: bool) -> Vec<String> {
+        let mut lines = Vec::new();
+
+        if header {
+            let mut headers: Vec<&str> = self.columns.iter().map(|c| c.header()).collect();
+            headers.push("Name");
+            if tree { headers.push("Depth") }
+            if directory.is_some() { headers.push("Directory") }
+            lines.push(delimited_record(&headers, delimiter));
+        }
+
+        for row in self.rows.iter() {
+            if let Some(ref cells) = row.cells {
+                let mut texts: Vec<String> = cells.iter().map(|c| c.text()).collect();
+                texts.push(row.name.text());
+                if tree { texts.push(row.depth.to_string()) }
+                if let Some(directory) = directory { texts.push(directory.to_owned()) }
+
+                let fields: Vec<&str> = texts.iter().map(|t| &**t).collect();
+                lines.push(delimited_record(&fields, delimiter));
+            }
+        }
+
+        lines
     }
 
     /// Render the table as a vector of Cells, to be displayed on standard output.
-    pub fn print_table(&self) -> Vec<Cell> {
+    pub fn print_table(&self, style: TreeStyle) -> Vec<Cell> {
         let mut stack = Vec::new();
         let mut cells = Vec::new();
 
@@ -669,11 +899,15 @@ impl<U> Table<U> where U: Users {
 
             if let Some(ref cells) = row.cells {
                 for (n, width) in column_widths.iter().enumerate() {
-                    match self.columns[n].alignment() {
-                        Alignment::Left  => { cell.append(&cells[n]); cell.add_spaces(width - cells[n].length); }
-                        Alignment::Right => { cell.add_spaces(width - cells[n].length); cell.append(&cells[n]); }
+                    let mut column = cells[n].clone();
+
+                    // Porcelain fields are separated by one space, so they
+                    // don’t change when another file’s field gets longer.
+                    if !self.porcelain {
+                        column.pad(*width, self.columns[n].alignment());
                     }
 
+                    cell.append(&column);
                     cell.add_spaces(1);
                 }
             }
@@ -681,9 +915,6 @@ impl<U> Table<U> where U: Users {
                 cell.add_spaces(total_width)
             }
 
-            let mut filename = String::new();
-            let mut filename_length = 0;
-
             // A stack tracks which tree characters should be printed. It's
             // necessary to maintain information about the previously-printed
             // lines, as the output will change based on whether the
@@ -698,25 +929,32 @@ impl<U> Table<U> where U: Users {
 
             stack[row.depth] = if row.last { TreePart::Corner } else { TreePart::Edge };
 
-            for i in 1 .. row.depth + 1 {
-                filename.push_str(&*self.colours.punctuation.paint(stack[i].ascii_art()).to_string());
-                filename_length += 4;
+            // The files at the top level don't get any tree characters,
+            // except in Markdown, where every row needs a bullet to be part
+            // of the list.
+            let first_level = if style == TreeStyle::Markdown { 0 } else { 1 };
+
+            for i in first_level .. row.depth + 1 {
+                cell.append(&Cell::coloured(self.colours.punctuation, "punctuation", stack[i].ascii_art(style)));
             }
 
             stack[row.depth] = if row.last { TreePart::Blank } else { TreePart::Line };
 
             // If any tree characters have been printed, then add an extra
             // space, which makes the output look much better.
-            if row.depth != 0 {
-                filename.push(' ');
-                filename_length += 1;
+            if row.depth >= first_level {
+                cell.add_spaces(1);
             }
 
             // Print the name without worrying about padding.
-            filename.push_str(&*row.name.text);
-            filename_length += row.name.length;
-
-            cell.append(&Cell { text: filename, length: filename_length });
+            if style == TreeStyle::Markdown {
+                for span in &row.name.spans {
+                    cell.spans.push(Span { text: markdown_escape(&span.text), .. span.clone() });
+                }
+            }
+            else {
+                cell.append(&row.name);
+            }
             cells.push(cell);
         }
 
@@ -725,6 +963,81 @@ impl<U> Table<U> where U: Users {
 }
 
 
+/// Render the size of a file as a cell, using the given format and the
+/// given locale’s way of writing numbers.
+pub fn render_size(size: f::Size, size_format: SizeFormat, numeric: &locale::Numeric, colours: &Colours) -> Cell {
+    use number_prefix::{binary_prefix, decimal_prefix, Prefixed, Standalone, PrefixNames};
+
+    if let f::Size::Some(offset) = size {
+        let result = match size_format {
+            SizeFormat::DecimalBytes  => decimal_prefix(offset as f64),
+            SizeFormat::BinaryBytes   => binary_prefix(offset as f64),
+            SizeFormat::JustBytes     => return Cell::coloured(colours.size.numbers, "size-numbers", &numeric.format_int(offset)),
+        };
+
+        match result {
+            Standalone(bytes)    => Cell::coloured(colours.size.numbers, "size-numbers", &*bytes.to_string()),
+            Prefixed(prefix, n)  => {
+                let number = if n < 10f64 { numeric.format_float(n, 1) } else { numeric.format_int(n as isize) };
+                let symbol = prefix.symbol();
+
+                let mut cell = Cell::coloured(colours.size.numbers, "size-numbers", &number[..]);
+                cell.append(&Cell::coloured(colours.size.unit, "size-unit", symbol));
+                cell
+            }
+        }
+    }
+    else {
+        Cell::coloured(colours.punctuation, "punctuation", "-")
+    }
+}
+
+
+/// Join the given fields into one record, separated by the delimiter.
+///
+/// Fields containing the delimiter, a quote, or a line break get surrounded
+/// by quotes, with the quotes inside them doubled up, which is how both
+/// spreadsheets and RFC 4180 expect them to be escaped.
+fn delimited_record(fields: &[&str], delimiter: char) -> String {
+    let mut record = String::new();
+
+    for (index, field) in fields.iter().enumerate() {
+        if index != 0 {
+            record.push(delimiter);
+        }
+
+        if field.contains(|c| c == delimiter || c == '"' || c == '\n' || c == '\r') {
+            record.push('"');
+            record.push_str(&*field.replace("\"", "\"\""));
+            record.push('"');
+        }
+        else {
+            record.push_str(field);
+        }
+    }
+
+    record
+}
+
+
+/// Put a backslash before each character in the text that Markdown would
+/// otherwise treat as formatting, so file names come out as they are.
+fn markdown_escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '\\' | '*' | '_' | '`' | '[' | ']'  => escaped.push('\\'),
+            _                                   => {},
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+
 #[derive(PartialEq, Debug, Clone)]
 enum TreePart {
 
@@ -742,12 +1055,24 @@ enum TreePart {
 }
 
 impl TreePart {
-    fn ascii_art(&self) -> &'static str {
-        match *self {
-            TreePart::Edge    => "├──",
-            TreePart::Line    => "│  ",
-            TreePart::Corner  => "└──",
-            TreePart::Blank   => "   ",
+    fn ascii_art(&self, style: TreeStyle) -> &'static str {
+        match (style, self) {
+            (TreeStyle::Unicode,  &TreePart::Edge)    => "├──",
+            (TreeStyle::Unicode,  &TreePart::Line)    => "│  ",
+            (TreeStyle::Unicode,  &TreePart::Corner)  => "└──",
+            (TreeStyle::Unicode,  &TreePart::Blank)   => "   ",
+
+            (TreeStyle::Ascii,    &TreePart::Edge)    => "|--",
+            (TreeStyle::Ascii,    &TreePart::Line)    => "|  ",
+            (TreeStyle::Ascii,    &TreePart::Corner)  => "`--",
+            (TreeStyle::Ascii,    &TreePart::Blank)   => "   ",
+
+            // Every level of a Markdown list is indented by the width of
+            // its parent’s bullet, whether or not the parent was last.
+            (TreeStyle::Markdown, &TreePart::Edge)    => "-",
+            (TreeStyle::Markdown, &TreePart::Line)    => "  ",
+            (TreeStyle::Markdown, &TreePart::Corner)  => "-",
+            (TreeStyle::Markdown, &TreePart::Blank)   => "  ",
         }
     }
 }
@@ -806,7 +1131,7 @@ pub mod test {
             table.users = users;
 
             let user = f::User(1000);
-            let expected = Cell::paint(Red.bold(), "enoch");
+            let expected = Cell::coloured(Red.bold(), "users-user_you", "enoch");
             assert_eq!(expected, table.render_user(user))
         }
 
@@ -819,7 +1144,7 @@ pub mod test {
             table.users = users;
 
             let user = f::User(1000);
-            let expected = Cell::paint(Cyan.bold(), "1000");
+            let expected = Cell::coloured(Cyan.bold(), "users-user_you", "1000");
             assert_eq!(expected, table.render_user(user));
         }
 
@@ -830,7 +1155,7 @@ pub mod test {
             table.users.add_user(newser(1000, "enoch", 100));
 
             let user = f::User(1000);
-            let expected = Cell::paint(Green.bold(), "enoch");
+            let expected = Cell::coloured(Green.bold(), "users-user_someone_else", "enoch");
             assert_eq!(expected, table.render_user(user));
         }
 
@@ -840,7 +1165,7 @@ pub mod test {
             table.colours.users.user_someone_else = Red.normal();
 
             let user = f::User(1000);
-            let expected = Cell::paint(Red.normal(), "1000");
+            let expected = Cell::coloured(Red.normal(), "users-user_someone_else", "1000");
             assert_eq!(expected, table.render_user(user));
         }
 
@@ -850,7 +1175,7 @@ pub mod test {
             table.colours.users.user_someone_else = Blue.underline();
 
             let user = f::User(2_147_483_648);
-            let expected = Cell::paint(Blue.underline(), "2147483648");
+            let expected = Cell::coloured(Blue.underline(), "users-user_someone_else", "2147483648");
             assert_eq!(expected, table.render_user(user));
         }
     }
@@ -869,7 +1194,7 @@ pub mod test {
             table.users = users;
 
             let group = f::Group(100);
-            let expected = Cell::paint(Fixed(101).normal(), "folk");
+            let expected = Cell::coloured(Fixed(101).normal(), "users-group_not_yours", "folk");
             assert_eq!(expected, table.render_group(group))
         }
 
@@ -882,7 +1207,7 @@ pub mod test {
             table.users = users;
 
             let group = f::Group(100);
-            let expected = Cell::paint(Fixed(87).normal(), "100");
+            let expected = Cell::coloured(Fixed(87).normal(), "users-group_not_yours", "100");
             assert_eq!(expected, table.render_group(group));
         }
 
@@ -897,7 +1222,7 @@ pub mod test {
             table.users = users;
 
             let group = f::Group(100);
-            let expected = Cell::paint(Fixed(64).normal(), "folk");
+            let expected = Cell::coloured(Fixed(64).normal(), "users-group_yours", "folk");
             assert_eq!(expected, table.render_group(group))
         }
 
@@ -912,7 +1237,7 @@ pub mod test {
             table.users = users;
 
             let group = f::Group(100);
-            let expected = Cell::paint(Fixed(31).normal(), "folk");
+            let expected = Cell::coloured(Fixed(31).normal(), "users-group_yours", "folk");
             assert_eq!(expected, table.render_group(group))
         }
 
@@ -922,8 +1247,106 @@ pub mod test {
             table.colours.users.group_not_yours = Blue.underline();
 
             let group = f::Group(2_147_483_648);
-            let expected = Cell::paint(Blue.underline(), "2147483648");
+            let expected = Cell::coloured(Blue.underline(), "users-group_not_yours", "2147483648");
             assert_eq!(expected, table.render_group(group));
         }
     }
+
+    mod delimited {
+        use super::*;
+        use super::super::delimited_record;
+
+        #[test]
+        fn directory_field() {
+            let mut table = Table::default();
+            table.columns = Vec::new();
+            table.add_file_with_cells(vec![], Cell::paint(Style::default(), "main.rs"), 0, true);
+
+            assert_eq!(table.print_delimited(',', false, Some("src"), true), vec![ "Name,Directory", "main.rs,src" ]);
+        }
+
+        #[test]
+        fn plain() {
+            assert_eq!("1.2k,Cargo.toml", delimited_record(&[ "1.2k", "Cargo.toml" ], ','));
+        }
+
+        #[test]
+        fn quoted() {
+            assert_eq!("\"a, b\",\"say \"\"hi\"\"\",\"line\nbreak\"",
+                       delimited_record(&[ "a, b", "say \"hi\"", "line\nbreak" ], ','));
+        }
+
+        #[test]
+        fn tabs() {
+            assert_eq!("a, b\t\"c\td\"", delimited_record(&[ "a, b", "c\td" ], '\t'));
+        }
+    }
+
+    mod tree {
+        use super::*;
+        use std::collections::HashMap;
+        use dir::Dir;
+        use fixture::Fixture;
+        use options::{Options, View};
+        use output::Summary;
+        use output::details::TreeStyle;
+
+        #[test]
+        fn only_files_descends_into_directories() {
+            let fixture = Fixture::new("tree-only-files");
+            let _ = fixture.dir("sub");
+            let _ = fixture.dir("empty");
+            let _ = fixture.file("sub/nested.txt", b"");
+
+            let (options, _) = Options::getopts(&[ "-T".to_string(), "--only-files".to_string() ]).unwrap();
+            let details = match options.view {
+                View::Details(d)  => d,
+                _                 => panic!("expected a details view"),
+            };
+
+            let dir = Dir::read_dir(fixture.path(), false).unwrap();
+            let files = dir.files(details.filter.dot_filter).filter_map(Result::ok).collect();
+
+            let mut table = Table::default();
+            details.add_files_to_table(&mut table, files, 0, &mut Summary::default(), &mut HashMap::new());
+            let names: Vec<String> = table.rows.iter().map(|r| r.name.text()).collect();
+            assert_eq!(names, vec![ "sub", "nested.txt" ]);
+        }
+
+        #[test]
+        fn summary_counts_total_sizes_once() {
+            let fixture = Fixture::new("summary-total-size");
+            let _ = fixture.dir("sub/deeper");
+            let _ = fixture.file("a.txt", &[ 0; 10 ]);
+            let _ = fixture.file("sub/b.txt", &[ 0; 20 ]);
+            let _ = fixture.file("sub/deeper/c.txt", &[ 0; 30 ]);
+
+            let args: Vec<String> = vec![ "-lT", "--total-size", "--summary" ].into_iter().map(String::from).collect();
+            let (options, _) = Options::getopts(&args).unwrap();
+            let details = match options.view {
+                View::Details(d)  => d,
+                _                 => panic!("expected a details view"),
+            };
+
+            let dir = Dir::read_dir(fixture.path(), false).unwrap();
+            let files = dir.files(details.filter.dot_filter).filter_map(Result::ok).collect();
+
+            let mut table = Table::default();
+            let mut summary = Summary::default();
+            details.add_files_to_table(&mut table, files, 0, &mut summary, &mut HashMap::new());
+
+            assert_eq!((summary.files, summary.directories, summary.size), (3, 2, 60));
+        }
+
+        #[test]
+        fn markdown_bullets_every_row() {
+            let mut table = Table::default();
+            table.columns = Vec::new();
+            table.add_file_with_cells(vec![], Cell::paint(Style::default(), "src"), 0, true);
+            table.add_file_with_cells(vec![], Cell::paint(Style::default(), "my_*notes*.md"), 1, true);
+
+            let lines: Vec<String> = table.print_table(TreeStyle::Markdown).iter().map(|c| c.text()).collect();
+            assert_eq!(lines, vec![ "- src", "  - my\\_\\*notes\\*.md" ]);
+        }
+    }
 }
@@ -1,15 +1,17 @@
 use colours::Colours;
 use file::File;
-use filetype::file_colour;
 
 use term_grid as grid;
 
+use super::{filename, PathFormat};
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+
+#[derive(PartialEq, Debug, Clone)]
 pub struct Grid {
     pub across: bool,
     pub console_width: usize,
     pub colours: Colours,
+    pub path_format: PathFormat,
 }
 
 impl Grid {
@@ -25,9 +27,11 @@ impl Grid {
         grid.reserve(files.len());
 
         for file in files.iter() {
+            let name = filename(file, &self.colours, false, &self.path_format);
+
             grid.add(grid::Cell {
-                contents:  file_colour(&self.colours, file).paint(&*file.name).to_string(),
-                width:     file.file_name_width(),
+                width:     name.width(),
+                contents:  name.to_string(),
             });
         }
 
@@ -37,7 +41,7 @@ impl Grid {
         else {
             // File names too long for a grid - drop down to just listing them!
             for file in files.iter() {
-                println!("{}", file_colour(&self.colours, file).paint(&*file.name));
+                println!("{}", filename(file, &self.colours, false, &self.path_format));
             }
         }
     }
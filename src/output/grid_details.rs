@@ -1,5 +1,8 @@
+use std::collections::HashMap;
 use std::iter::repeat;
 
+use num_cpus;
+use scoped_threadpool::Pool;
 use users::OSUsers;
 use term_grid as grid;
 
@@ -10,8 +13,9 @@ use file::File;
 use output::column::{Column, Cell};
 use output::details::{Details, Table};
 use output::grid::Grid;
+use output::filename;
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(PartialEq, Debug, Clone)]
 pub struct GridDetails {
     pub grid: Grid,
     pub details: Details,
@@ -25,13 +29,17 @@ fn file_has_xattrs(file: &File) -> bool {
 }
 
 impl GridDetails {
-    pub fn view(&self, dir: Option<&Dir>, files: &[File]) {
+    pub fn view(&self, dir: Option<&Dir>, mut files: Vec<File>) {
+        let mut pool = Pool::new(num_cpus::get() as u32);
+        self.details.add_total_sizes(&mut pool, &mut files, &mut HashMap::new());
+        let files = &*files;
+
         let columns_for_dir = match self.details.columns {
-            Some(cols) => cols.for_dir(dir),
+            Some(cols) => cols.for_dir(dir, self.details.filter.sniffs()),
             None => Vec::new(),
         };
 
-        let mut first_table = Table::with_options(self.details.colours, columns_for_dir.clone());
+        let mut first_table = Table::with_options(self.details.colours.clone(), columns_for_dir.clone(), false);
         let cells: Vec<_> = files.iter().map(|file| first_table.cells_for_file(file, file_has_xattrs(file))).collect();
 
         let mut last_working_table = self.make_grid(1, &*columns_for_dir, files, cells.clone());
@@ -55,7 +63,7 @@ impl GridDetails {
     }
 
     fn make_table(&self, columns_for_dir: &[Column]) -> Table<OSUsers> {
-        let mut table = Table::with_options(self.details.colours, columns_for_dir.into());
+        let mut table = Table::with_options(self.details.colours.clone(), columns_for_dir.into(), false);
         if self.details.header { table.add_header() }
         table
     }
@@ -79,10 +87,11 @@ impl GridDetails {
                     i / original_height
                 };
 
-            tables[index].add_file_with_cells(row, file, 0, false, false);
+            let name = filename(file, &self.details.colours, false, &self.details.path_format);
+            tables[index].add_file_with_cells(row, name, 0, false);
         }
 
-        let columns: Vec<_> = tables.iter().map(|t| t.print_table()).collect();
+        let columns: Vec<_> = tables.iter().map(|t| t.print_table(self.details.tree_style)).collect();
 
         let direction = if self.grid.across { grid::Direction::LeftToRight }
                                        else { grid::Direction::TopToBottom };
@@ -97,8 +106,8 @@ impl GridDetails {
                 for column in columns.iter() {
                     if row < column.len() {
                         let cell = grid::Cell {
-                            contents: column[row].text.clone(),
-                            width:    column[row].length,
+                            contents: column[row].to_string(),
+                            width:    column[row].width(),
                         };
 
                         grid.add(cell);
@@ -110,8 +119,8 @@ impl GridDetails {
             for column in columns.iter() {
                 for cell in column.iter() {
                     let cell = grid::Cell {
-                        contents: cell.text.clone(),
-                        width:    cell.length,
+                        contents: cell.to_string(),
+                        width:    cell.width(),
                     };
 
                     grid.add(cell);
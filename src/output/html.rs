@@ -0,0 +1,80 @@
+//! Rendering cells as **HTML**, instead of as text for a terminal.
+//!
+//! Each span of text in a cell gets wrapped in a `span` element whose CSS
+//! class is named after the colour it would have been painted with, such as
+//! `filetypes-directory` or `perms-user_read`, so the listing can be styled
+//! by a stylesheet. Text painted without a colour, such as padding, is
+//! written out as-is. The views print their lines inside a `pre` element, so
+//! the columns still line up.
+
+use output::column::Cell;
+
+
+/// Render the given cell as HTML, using the name of the colour each span
+/// was painted with as its class.
+pub fn render(cell: &Cell) -> String {
+    let mut html = String::new();
+
+    for span in &cell.spans {
+        match span.class {
+            Some(ref class) => {
+                html.push_str(&format!("<span class=\"{}\">{}</span>", class, escape(&span.text)));
+            },
+            None => html.push_str(&*escape(&span.text)),
+        }
+    }
+
+    html
+}
+
+/// Escape the characters that have special meanings in HTML.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        match c {
+            '&'  => escaped.push_str("&amp;"),
+            '<'  => escaped.push_str("&lt;"),
+            '>'  => escaped.push_str("&gt;"),
+            '"'  => escaped.push_str("&quot;"),
+            c    => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{escape, render};
+    use ansi_term::Colour::Blue;
+    use colours::Colours;
+    use output::column::Cell;
+
+    #[test]
+    fn escaping() {
+        assert_eq!(escape("<a & \"b\">"), "&lt;a &amp; &quot;b&quot;&gt;")
+    }
+
+    #[test]
+    fn classes() {
+        let colours = Colours::plain();
+
+        let mut cell = Cell::coloured(colours.filetypes.directory, "filetypes-directory", "src");
+        cell.add_spaces(1);
+        cell.append(&Cell::coloured(colours.punctuation, "punctuation", "->"));
+
+        assert_eq!(render(&cell),
+                   "<span class=\"filetypes-directory\">src</span> <span class=\"punctuation\">-&gt;</span>")
+    }
+
+    #[test]
+    fn shared_styles() {
+        let mut cell = Cell::coloured(Blue.bold(), "filetypes-directory", "src");
+        cell.append(&Cell::coloured(Blue.bold(), "filetypes-source", ".rs"));
+
+        assert_eq!(render(&cell),
+                   "<span class=\"filetypes-directory\">src</span><span class=\"filetypes-source\">.rs</span>")
+    }
+}
@@ -0,0 +1,215 @@
+//! The **JSON** view prints out the details of each file as a JSON object,
+//! so that exa’s output can be read by other programs without having to
+//! parse its columns.
+//!
+//! Each file gets printed as one object on its own line, containing the raw
+//! values that the details view would otherwise render into cells:
+//!
+//! ```text
+//! {"blocks":8,"group":{"gid":20,"name":"staff"},"inode":8627431,"links":1,
+//!  "modified":{"epoch":1443883590,"iso":"2015-10-03T14:46:30Z"},"name":"Cargo.toml", ...}
+//! ```
+//!
+//! Fields that don’t apply to a file, such as the size of a directory or the
+//! target of a regular file, are `null`. In tree mode, directories also get
+//! a `children` array containing the objects for the files inside them,
+//! so a whole tree is printed as one object per top-level file.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::os::unix::fs::PermissionsExt;
+
+use rustc_serialize::json::{Json as JsonValue, ToJson};
+use users::{OSUsers, Users};
+
+use feature::xattr::{self, FileAttributes};
+use file::fields as f;
+use file::File;
+use options::{FileFilter, RecurseOptions};
+use output::{iso_8601, kind};
+
+
+#[derive(PartialEq, Debug, Clone)]
+pub struct Json {
+
+    /// Whether to recurse through directories with a tree view, and if so,
+    /// which options to use.
+    pub recurse: Option<RecurseOptions>,
+
+    /// How to sort and filter the files inside directories when recursing.
+    pub filter: FileFilter,
+}
+
+impl Json {
+    pub fn view(&self, files: Vec<File>) {
+        let mut users = OSUsers::empty_cache();
+
+        for file in files {
+            if let Some(json) = self.file_to_json(&file, &mut users, 0) {
+                println!("{}", json);
+            }
+        }
+    }
+
+    /// Convert one file to a JSON object, along with the files inside it if
+    /// it’s a directory being recursed into.
+    ///
+    /// Just like in the details view, a file that wasn’t given on the
+    /// command line and doesn’t match the filter is left out, unless it’s a
+    /// directory with matching files somewhere inside it, in which case
+    /// `None` is returned only if it turns out to have no children.
+    fn file_to_json<U: Users>(&self, file: &File, users: &mut U, depth: usize) -> Option<JsonValue> {
+        let hidden = file.dir.is_some() && !self.filter.matches(file);
+        let mut object = BTreeMap::new();
+
+        if let Some(r) = self.recurse {
+            if file.is_directory() && r.tree && !r.is_too_deep(depth) && !file.is_all_all {
+                let mut children = Vec::new();
+
+                if let Ok(dir) = file.to_dir(true) {
+                    let mut files = Vec::new();
+                    for file in dir.files(self.filter.dot_filter) {
+                        if let Ok(file) = file {
+                            files.push(file);
+                        }
+                    }
+
+                    self.filter.filter_files(&mut files);
+                    self.filter.sort_files(&mut files);
+
+                    for child in &files {
+                        if let Some(json) = self.file_to_json(child, users, depth + 1) {
+                            children.push(json);
+                        }
+                    }
+                }
+
+                if hidden && children.is_empty() {
+                    return None;
+                }
+
+                let _ = object.insert("children".to_owned(), JsonValue::Array(children));
+            }
+        }
+
+        if hidden && !object.contains_key("children") {
+            return None;
+        }
+
+        let user = file.user();
+        let user_name = users.get_user_by_uid(user.0).map(|u| u.name);
+        let group = file.group();
+        let group_name = users.get_group_by_gid(group.0).map(|g| g.name);
+
+        let mut fields = vec![
+            ("name",         file.name.to_json()),
+            ("path",         file.path.to_string_lossy().to_json()),
+            ("type",         kind(file).to_json()),
+            ("permissions",  (file.metadata.permissions().mode() & 0o7777).to_json()),
+            ("size",         size_to_json(file.size())),
+            ("blocks",       blocks_to_json(file.blocks())),
+            ("inode",        file.inode().0.to_json()),
+            ("links",        file.links().count.to_json()),
+            ("user",         owner_to_json("uid", user.0 as u64, user_name)),
+            ("group",        owner_to_json("gid", group.0 as u64, group_name)),
+            ("modified",     time_to_json(file.modified_time())),
+            ("accessed",     time_to_json(file.accessed_time())),
+            ("created",      time_to_json(file.created_time())),
+            ("git",          git_to_json(file)),
+            ("target",       target_to_json(file)),
+        ];
+
+        if xattr::ENABLED {
+            let xattrs = match file.path.attributes() {
+                Ok(xattrs) => xattrs.into_iter().map(|a| {
+                    let mut attr = BTreeMap::new();
+                    let _ = attr.insert("name".to_owned(), a.name.to_json());
+                    let _ = attr.insert("size".to_owned(), a.size.to_json());
+                    JsonValue::Object(attr)
+                }).collect(),
+                Err(_) => Vec::new(),
+            };
+
+            fields.push(("xattrs", JsonValue::Array(xattrs)));
+        }
+
+        for (key, value) in fields {
+            let _ = object.insert(key.to_owned(), value);
+        }
+
+        Some(JsonValue::Object(object))
+    }
+}
+
+fn size_to_json(size: f::Size) -> JsonValue {
+    match size {
+        f::Size::Some(bytes)  => bytes.to_json(),
+        f::Size::None         => JsonValue::Null,
+    }
+}
+
+fn blocks_to_json(blocks: f::Blocks) -> JsonValue {
+    match blocks {
+        f::Blocks::Some(count)  => count.to_json(),
+        f::Blocks::None         => JsonValue::Null,
+    }
+}
+
+/// A user or group, as both its ID and its name, if it has one.
+fn owner_to_json(id_key: &str, id: u64, name: Option<String>) -> JsonValue {
+    let mut owner = BTreeMap::new();
+    let _ = owner.insert(id_key.to_owned(), id.to_json());
+    let _ = owner.insert("name".to_owned(), name.to_json());
+    JsonValue::Object(owner)
+}
+
+/// A timestamp, as both a number of seconds since the epoch, and an
+/// ISO-8601 string in UTC.
+#[allow(trivial_numeric_casts)]
+fn time_to_json(time: f::Time) -> JsonValue {
+    let epoch = time.0 as i64;
+    let iso = iso_8601(time);
+
+    let mut timestamp = BTreeMap::new();
+    let _ = timestamp.insert("epoch".to_owned(), epoch.to_json());
+    let _ = timestamp.insert("iso".to_owned(), iso.to_json());
+    JsonValue::Object(timestamp)
+}
+
+/// The Git status of a file, or `null` if it isn’t in a repository.
+fn git_to_json(file: &File) -> JsonValue {
+    match file.dir {
+        Some(dir) if dir.has_git_repo() => {
+            let git = file.git_status();
+            let mut status = BTreeMap::new();
+            let _ = status.insert("staged".to_owned(), git_status_name(git.staged).to_json());
+            let _ = status.insert("unstaged".to_owned(), git_status_name(git.unstaged).to_json());
+            JsonValue::Object(status)
+        },
+        _ => JsonValue::Null,
+    }
+}
+
+fn git_status_name(status: f::GitStatus) -> &'static str {
+    match status {
+        f::GitStatus::NotModified  => "unmodified",
+        f::GitStatus::New          => "new",
+        f::GitStatus::Modified     => "modified",
+        f::GitStatus::Deleted      => "deleted",
+        f::GitStatus::Renamed      => "renamed",
+        f::GitStatus::TypeChange   => "typechange",
+    }
+}
+
+/// The path a symlink points to, exactly as it was written, or `null` if
+/// the file isn’t a symlink.
+fn target_to_json(file: &File) -> JsonValue {
+    if !file.is_link() {
+        return JsonValue::Null;
+    }
+
+    match fs::read_link(&file.path) {
+        Ok(path)  => path.to_string_lossy().to_json(),
+        Err(_)    => JsonValue::Null,
+    }
+}
@@ -1,19 +1,26 @@
 use colours::Colours;
 use file::File;
 
-use super::filename;
+use super::{filename, html, PathFormat};
 
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Lines {
     pub colours: Colours,
+    pub path_format: PathFormat,
+
+    /// Whether to print each line as HTML, rather than for a terminal.
+    pub html: bool,
 }
 
 /// The lines view literally just displays each file, line-by-line.
 impl Lines {
     pub fn view(&self, files: &[File]) {
         for file in files {
-            println!("{}", filename(file, &self.colours, true));
+            let name = filename(file, &self.colours, true, &self.path_format);
+
+            if self.html { println!("{}", html::render(&name)) }
+                    else { println!("{}", name) }
         }
     }
 }
@@ -1,42 +1,237 @@
-use ansi_term::ANSIStrings;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+use ansi_term::Style;
+
+use scoped_threadpool::Pool;
+
+use datetime::local::{LocalDateTime, DatePiece, TimePiece};
 
 use colours::Colours;
+use file::fields as f;
 use file::File;
-use filetype::file_colour;
+use filetype::{file_class, file_colour};
+use output::column::Cell;
 
 pub use self::details::Details;
 pub use self::grid::Grid;
 pub use self::lines::Lines;
 pub use self::grid_details::GridDetails;
+pub use self::json::Json;
+pub use self::count::Count;
+pub use self::print0::Print0;
+pub use self::summary::Summary;
 
 mod grid;
 pub mod details;
 mod lines;
 mod grid_details;
+mod json;
+mod count;
+mod print0;
+mod summary;
 pub mod column;
+pub mod html;
+
+
+/// Read the start of each regular file in the given files to work out its
+/// format. Like the extended attributes, this means touching every file, so
+/// it’s done using the given pool of threads.
+pub fn sniff_files(pool: &mut Pool, files: &mut [File]) {
+    pool.scoped(|scoped| {
+        for file in files.iter_mut().filter(|f| f.is_file()) {
+            scoped.execute(move || {
+                file.magic = file.sniff();
+            });
+        }
+    });
+}
+
+
+/// How to display each file’s name.
+#[derive(PartialEq, Debug, Clone)]
+pub enum PathFormat {
+
+    /// Just the file’s name, without the directory it’s in.
+    Bare,
+
+    /// The file’s full, canonical path, starting from the root.
+    Absolute,
+
+    /// The file’s path, relative to the given directory, which must already
+    /// have been canonicalised.
+    RelativeTo(PathBuf),
+}
+
+impl Default for PathFormat {
+    fn default() -> PathFormat {
+        PathFormat::Bare
+    }
+}
+
+/// Render the name of a file, in the given path format, as a cell. Any
+/// directories leading up to the file get painted like the path of a
+/// symlink target, and the last component gets painted like a file name.
+///
+/// If `links` is set, symlinks also get their targets displayed, so this
+/// should only be used where the name is the last thing on a line.
+pub fn filename(file: &File, colours: &Colours, links: bool, format: &PathFormat) -> Cell {
+    let style = file_colour(colours, file);
+    let class = file_class(colours, file);
 
+    let mut cell = match *format {
+        PathFormat::Bare => Cell::coloured(style, class, &*file.name),
+        PathFormat::Absolute => path_cell(&canonical_path(file), colours, style, class),
+        PathFormat::RelativeTo(ref base) => path_cell(&relative_path(&canonical_path(file), base), colours, style, class),
+    };
 
-pub fn filename(file: &File, colours: &Colours, links: bool) -> String {
     if links && file.is_link() {
-        symlink_filename(file, colours)
+        cell.append(&symlink_target(file, colours));
+    }
+
+    cell
+}
+
+fn path_cell(path: &Path, colours: &Colours, style: Style, class: String) -> Cell {
+    let name = match path.file_name() {
+        Some(name)  => name.to_string_lossy().into_owned(),
+        None        => path.to_string_lossy().into_owned(),
+    };
+
+    let prefix = match path.parent() {
+        Some(parent) if path.file_name().is_some() && parent != Path::new("") => {
+            let mut prefix = parent.to_string_lossy().into_owned();
+            if !prefix.ends_with('/') { prefix.push('/') }
+            prefix
+        },
+        _ => String::new(),
+    };
+
+    let name = if name.is_empty() { ".".to_owned() } else { name };
+
+    if prefix.is_empty() {
+        Cell::coloured(style, class, &*name)
+    }
+    else {
+        let mut cell = Cell::coloured(colours.symlink_path, "symlink_path", &*prefix);
+        cell.append(&Cell::coloured(style, class, &*name));
+        cell
+    }
+}
+
+/// The canonical path of a file. Symlinks don’t get followed: only the
+/// directory they’re in gets canonicalised, so that the path still points
+/// to the link. If the path can’t be canonicalised, the path the file was
+/// found at gets used instead.
+fn canonical_path(file: &File) -> PathBuf {
+    let canonical = if file.is_link() {
+        let parent = match file.path.parent() {
+            Some(parent) if parent != Path::new("") => parent,
+            _                                       => Path::new("."),
+        };
+
+        fs::canonicalize(parent).map(|p| p.join(&*file.name))
     }
     else {
-        let style = file_colour(colours, file);
-        style.paint(&*file.name).to_string()
+        fs::canonicalize(&file.path)
+    };
+
+    canonical.unwrap_or_else(|_| file.path.clone())
+}
+
+/// Work out the path that leads from the `base` directory to `path`, both of
+/// which should be canonical.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    let path_components: Vec<Component> = path.components().collect();
+    let base_components: Vec<Component> = base.components().collect();
+
+    let common = path_components.iter().zip(base_components.iter())
+                                .take_while(|&(a, b)| a == b)
+                                .count();
+
+    let mut relative = PathBuf::new();
+
+    for _ in common .. base_components.len() {
+        relative.push("..");
     }
+
+    for component in &path_components[common ..] {
+        relative.push(component.as_os_str());
+    }
+
+    relative
+}
+
+/// The kind of file this is, as the JSON and count views name it. These are
+/// the singular forms of the words the `--only` option takes, such as
+/// `directory` for `directories`, along with `other` for anything else.
+pub fn kind(file: &File) -> &'static str {
+    if file.is_directory()     { "directory" }
+    else if file.is_file()     { "file" }
+    else if file.is_link()     { "link" }
+    else if file.is_pipe()     { "pipe" }
+    else if file.is_socket()   { "socket" }
+    else if file.is_device()   { "device" }
+    else                       { "other" }
+}
+
+/// The kinds returned by `kind`, in the order they get counted in.
+pub static KINDS: &'static [&'static str] = &[
+    "directory", "file", "link", "pipe", "socket", "device", "other",
+];
+
+/// Format a timestamp as an ISO-8601 date and time in UTC, down to the
+/// second, such as `2015-10-03T14:46:30Z`. Unlike the dates in the details
+/// view, this doesn’t depend on the user’s locale or the current year.
+#[allow(trivial_numeric_casts)]
+pub fn iso_8601(time: f::Time) -> String {
+    let date = LocalDateTime::at(time.0 as i64);
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+            date.year(), date.month().months_from_january() + 1, date.day(),
+            date.hour(), date.minute(), date.second())
 }
 
-fn symlink_filename(file: &File, colours: &Colours) -> String {
+fn symlink_target(file: &File, colours: &Colours) -> Cell {
+    let mut cell = Cell::empty();
+    cell.add_spaces(1);
+
     match file.link_target() {
-        Ok(target) => format!("{} {} {}",
-                              file_colour(colours, file).paint(&*file.name),
-                              colours.punctuation.paint("->"),
-                              ANSIStrings(&[ colours.symlink_path.paint(target.path_prefix()),
-                                             file_colour(colours, &target).paint(target.name) ])),
-
-        Err(filename) => format!("{} {} {}",
-                                 file_colour(colours, file).paint(&*file.name),
-                                 colours.broken_arrow.paint("->"),
-                                 colours.broken_filename.paint(filename)),
+        Ok(target) => {
+            cell.append(&Cell::coloured(colours.punctuation, "punctuation", "->"));
+            cell.add_spaces(1);
+            cell.append(&Cell::coloured(colours.symlink_path, "symlink_path", &*target.path_prefix()));
+            cell.append(&Cell::coloured(file_colour(colours, &target), file_class(colours, &target), &*target.name));
+        },
+
+        Err(filename) => {
+            cell.append(&Cell::coloured(colours.broken_arrow, "broken_arrow", "->"));
+            cell.add_spaces(1);
+            cell.append(&Cell::coloured(colours.broken_filename, "broken_filename", &*filename));
+        },
+    }
+
+    cell
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::relative_path;
+    use std::path::{Path, PathBuf};
+
+    #[test]
+    fn inside() {
+        assert_eq!(relative_path(Path::new("/home/ben/src/exa"), Path::new("/home/ben")), PathBuf::from("src/exa"));
+    }
+
+    #[test]
+    fn outside() {
+        assert_eq!(relative_path(Path::new("/srv/www"), Path::new("/home/ben")), PathBuf::from("../../srv/www"));
+    }
+
+    #[test]
+    fn same() {
+        assert_eq!(relative_path(Path::new("/home/ben"), Path::new("/home/ben")), PathBuf::new());
     }
 }
@@ -0,0 +1,79 @@
+use std::ffi::OsStr;
+use std::io::{self, Write};
+use std::os::unix::ffi::OsStrExt;
+
+use file::File;
+use options::{FileFilter, RecurseOptions};
+
+
+/// The **print0** view writes the bare path of each file, followed by a
+/// NUL byte instead of a newline, so that the list can be split up safely
+/// by programs like `xargs -0` even when file names contain newlines.
+///
+/// There’s no colour and no symlink arrows. Files inside the listed
+/// directory get written as just their names, unless exa is recursing, in
+/// which case their whole paths get written instead, so they can be found
+/// from the current directory.
+#[derive(PartialEq, Debug, Clone)]
+pub struct Print0 {
+
+    /// Whether to recurse through directories, and if so, which options to
+    /// use. In tree mode, the recursion is done by this view.
+    pub recurse: Option<RecurseOptions>,
+
+    /// How to sort and filter the files inside directories when recursing.
+    pub filter: FileFilter,
+}
+
+impl Print0 {
+    pub fn view(&self, files: &[File]) {
+        let stdout = io::stdout();
+        let mut stdout = stdout.lock();
+
+        for file in files {
+            if self.write_file(&mut stdout, file, 0).is_err() {
+                return;
+            }
+        }
+    }
+
+    /// Write the path of one file, and then, in tree mode, the paths of all
+    /// the files inside it. Apart from the files given on the command line,
+    /// files that don’t match the filter are left out, though directories
+    /// still get recursed into.
+    fn write_file<W: Write>(&self, w: &mut W, file: &File, depth: usize) -> io::Result<()> {
+        if file.dir.is_none() || self.filter.matches(file) {
+            // The file’s name has been through a lossy conversion to UTF-8,
+            // so its raw bytes get taken from its path instead. The only
+            // exceptions are `.` and `..`, whose paths don’t end in them.
+            let path = if self.recurse.is_some() || file.dir.is_none() {
+                file.path.as_os_str()
+            }
+            else if file.is_all_all {
+                OsStr::new(&*file.name)
+            }
+            else {
+                file.path.file_name().unwrap_or(file.path.as_os_str())
+            };
+
+            try!(w.write_all(path.as_bytes()));
+            try!(w.write_all(b"\0"));
+        }
+
+        if let Some(r) = self.recurse {
+            if file.is_directory() && r.tree && !r.is_too_deep(depth) && !file.is_all_all {
+                if let Ok(dir) = file.to_dir(self.filter.should_scan_for_git()) {
+                    let mut files: Vec<File> = dir.files(self.filter.dot_filter).filter_map(Result::ok).collect();
+                    self.filter.filter_files(&mut files);
+                    self.filter.sort_files(&mut files);
+
+                    for child in &files {
+                        try!(self.write_file(w, child, depth + 1));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
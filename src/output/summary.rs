@@ -0,0 +1,132 @@
+use std::ops::Add;
+
+use ansi_term::Style;
+use locale;
+
+use colours::Colours;
+use file::fields as f;
+use file::File;
+use output::column::{Cell, SizeFormat};
+use output::details::render_size;
+
+
+/// A **Summary** counts up the files in a listing, so that a line of totals
+/// can be printed after it.
+///
+/// Only the files that actually get listed are counted: the children of a
+/// directory after they've been filtered. The size only counts files that
+/// have one, so directories don't add to it.
+#[derive(PartialEq, Debug, Copy, Clone, Default)]
+pub struct Summary {
+    pub files:        u64,
+    pub directories:  u64,
+    pub symlinks:     u64,
+    pub others:       u64,
+    pub size:         u64,
+    pub blocks:       u64,
+}
+
+impl Summary {
+
+    /// Count up the given files.
+    pub fn of(files: &[File]) -> Summary {
+        let mut summary = Summary::default();
+
+        for file in files {
+            summary.add_file(file);
+        }
+
+        summary
+    }
+
+    /// Count up one more file.
+    pub fn add_file(&mut self, file: &File) {
+        if file.is_link()            { self.symlinks += 1 }
+        else if file.is_directory()  { self.directories += 1 }
+        else if file.is_file()       { self.files += 1 }
+        else                         { self.others += 1 }
+
+        // A directory's size can be the total of everything inside it,
+        // which would count those files twice.
+        if !file.is_directory() {
+            if let f::Size::Some(size) = file.size() {
+                self.size += size;
+            }
+        }
+
+        if let f::Blocks::Some(blocks) = file.blocks() {
+            self.blocks += blocks;
+        }
+    }
+
+    /// Render this summary as a line, such as “Total: 3 files, 1 directory,
+    /// 0 symlinks, 0 others, 1.2k, 16 blocks”, with the size in the given
+    /// format, and the numbers in the given locale.
+    pub fn render(&self, label: &str, size_format: SizeFormat, numeric: &locale::Numeric, colours: &Colours) -> Cell {
+        let mut cell = Cell::coloured(colours.header, "header", label);
+        cell.append(&Cell::coloured(colours.punctuation, "punctuation", ":"));
+
+        let counts = [
+            (self.files,        "file",       "files"),
+            (self.directories,  "directory",  "directories"),
+            (self.symlinks,     "symlink",    "symlinks"),
+            (self.others,       "other",      "others"),
+        ];
+
+        for &(count, singular, plural) in counts.iter() {
+            cell.add_spaces(1);
+            cell.append(&Cell::coloured(colours.size.numbers, "size-numbers", &numeric.format_int(count)));
+            cell.add_spaces(1);
+            cell.append(&Cell::paint(Style::default(), if count == 1 { singular } else { plural }));
+            cell.append(&Cell::coloured(colours.punctuation, "punctuation", ","));
+        }
+
+        cell.add_spaces(1);
+        cell.append(&render_size(f::Size::Some(self.size), size_format, numeric, colours));
+        cell.append(&Cell::coloured(colours.punctuation, "punctuation", ","));
+        cell.add_spaces(1);
+        cell.append(&Cell::coloured(colours.blocks, "blocks", &numeric.format_int(self.blocks)));
+        cell.append(&Cell::paint(Style::default(), " blocks"));
+
+        cell
+    }
+}
+
+impl Add for Summary {
+    type Output = Summary;
+
+    fn add(self, other: Summary) -> Summary {
+        Summary {
+            files:        self.files + other.files,
+            directories:  self.directories + other.directories,
+            symlinks:     self.symlinks + other.symlinks,
+            others:       self.others + other.others,
+            size:         self.size + other.size,
+            blocks:       self.blocks + other.blocks,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::Summary;
+    use colours::Colours;
+    use locale;
+    use output::column::SizeFormat;
+
+    #[test]
+    fn plurals() {
+        let summary = Summary { files: 1, directories: 2, symlinks: 0, others: 1, size: 1500, blocks: 8 };
+        let numeric = locale::Numeric::english();
+        assert_eq!(summary.render("Total", SizeFormat::DecimalBytes, &numeric, &Colours::plain()).text(),
+                   "Total: 1 file, 2 directories, 0 symlinks, 1 other, 1.5k, 8 blocks")
+    }
+
+    #[test]
+    fn adding() {
+        let one = Summary { files: 1, directories: 2, symlinks: 0, others: 1, size: 1500, blocks: 8 };
+        let two = Summary { files: 3, directories: 0, symlinks: 1, others: 0, size: 500, blocks: 8 };
+        assert_eq!(one + two, Summary { files: 4, directories: 2, symlinks: 1, others: 1, size: 2000, blocks: 16 })
+    }
+}
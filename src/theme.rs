@@ -0,0 +1,299 @@
+//! Loading **themes**, which are files that set exa’s colours, from the
+//! user’s configuration directory.
+//!
+//! A theme is written in a small subset of TOML. Each table is named after
+//! one of the groups of colours, and each key in it after one of the colours
+//! in that group, with the colours that aren’t in a group coming before the
+//! first table. Two more tables give styles to particular file names and
+//! extensions:
+//!
+//! ```toml
+//! punctuation = "244"
+//!
+//! [filetypes]
+//! directory = "bold blue"
+//! executable = "bold #5fd700"
+//!
+//! [perms]
+//! user_read = "yellow underline"
+//!
+//! [extensions]
+//! rs = "#dea584"
+//!
+//! [filenames]
+//! Makefile = "bold yellow on black"
+//! ```
+//!
+//! Each style is a list of words: `bold`, `dimmed`, `italic`, `underline`,
+//! `blink`, `reverse`, or `hidden`, the name of one of the eight basic
+//! colours, the number of one of the 256 colours, or a 24-bit colour written
+//! as `#rrggbb`. A colour that comes after `on` is used for the background.
+//! As colours only get printed as one of the 256 colours, 24-bit ones get
+//! turned into the closest one.
+//!
+//! A theme’s name can’t contain a `/` or `..`, so it can only ever refer to
+//! a file in the themes directory.
+
+use std::ascii::AsciiExt;
+
+use ansi_term::Style;
+use ansi_term::Colour::{self, Black, Red, Green, Yellow, Blue, Purple, Cyan, White, Fixed};
+
+use config::{self, Value};
+use file::File;
+
+
+/// The contents of a theme file.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct Theme {
+
+    /// The style of each colour the theme sets, along with the name of the
+    /// table it was in (which is empty for the ones outside a table) and its
+    /// key in that table.
+    pub styles: Vec<(String, String, Style)>,
+
+    /// The styles for particular file names and extensions.
+    pub names: NameStyles,
+}
+
+/// The styles that a theme gives to files with particular names or
+/// extensions. These take priority over the style for a file’s category.
+#[derive(PartialEq, Debug, Default, Clone)]
+pub struct NameStyles {
+    filenames: Vec<(String, Style)>,
+    extensions: Vec<(String, Style)>,
+}
+
+impl NameStyles {
+
+    /// The style to paint the given file’s name with, if there’s one that
+    /// applies to it. Its full name is looked at before its extension, and
+    /// directories don’t get styled by their extensions.
+    pub fn style(&self, file: &File) -> Option<Style> {
+        if let Some(&(_, style)) = self.filenames.iter().find(|&&(ref name, _)| *name == file.name) {
+            return Some(style);
+        }
+
+        if file.is_directory() {
+            return None;
+        }
+
+        match file.ext {
+            Some(ref ext) => self.extensions.iter().find(|&&(ref e, _)| e == ext).map(|&(_, style)| style),
+            None          => None,
+        }
+    }
+}
+
+impl Theme {
+
+    /// Load a theme from the user’s configuration directory. With a name,
+    /// the theme is read from `themes/NAME.toml`, and it’s an error for it
+    /// to be missing. Without one, the theme in `theme.toml` is used if
+    /// there is one.
+    pub fn load(name: Option<&str>) -> Result<Option<Theme>, String> {
+        if let Some(name) = name {
+            if name.is_empty() || name.contains('/') || name.contains("..") {
+                return Err(format!("{}: invalid theme name", name));
+            }
+        }
+
+        let directory = match config::directory() {
+            Some(directory) => directory,
+            None if name.is_some() => return Err("can’t find the configuration directory".to_owned()),
+            None => return Ok(None),
+        };
+
+        let path = match name {
+            Some(name) => directory.join("themes").join(format!("{}.toml", name)),
+            None       => directory.join("theme.toml"),
+        };
+
+        let contents = match try!(config::read(&path)) {
+            Some(contents)  => contents,
+            None if name.is_some() => return Err(format!("{}: no such theme", path.display())),
+            None            => return Ok(None),
+        };
+
+        match Theme::parse(&contents) {
+            Ok(theme) => Ok(Some(theme)),
+            Err(e)    => Err(format!("{}: {}", path.display(), e)),
+        }
+    }
+
+    /// Parse the contents of a theme file. Returns the number of the first
+    /// line that can’t be parsed, along with what’s wrong with it, as an
+    /// error.
+    pub fn parse(input: &str) -> Result<Theme, String> {
+        let mut theme = Theme::default();
+
+        for entry in try!(config::parse(input)) {
+            let style = match entry.value {
+                Value::String(ref s)  => parse_style(s),
+                Value::List(_)        => None,
+            };
+
+            let style = match style {
+                Some(style) => style,
+                None        => return Err(entry.error("invalid style")),
+            };
+
+            match &*entry.table {
+                "filenames"   => theme.names.filenames.push((entry.key, style)),
+                "extensions"  => theme.names.extensions.push((entry.key.to_ascii_lowercase(), style)),
+                _             => theme.styles.push((entry.table, entry.key, style)),
+            }
+        }
+
+        Ok(theme)
+    }
+}
+
+/// Parse a style written as a list of words, such as `bold #ff8700 on black`.
+pub fn parse_style(input: &str) -> Option<Style> {
+    let mut foreground = None;
+    let mut background = None;
+    let mut modifiers = Vec::new();
+    let mut words = input.split_whitespace();
+
+    while let Some(word) = words.next() {
+        match word {
+            "bold" | "dimmed" | "italic" | "underline" | "blink" | "reverse" | "hidden" => modifiers.push(word),
+            "on"  => match words.next().and_then(parse_colour) {
+                Some(colour)  => background = Some(colour),
+                None          => return None,
+            },
+            _     => match parse_colour(word) {
+                Some(colour)  => foreground = Some(colour),
+                None          => return None,
+            },
+        }
+    }
+
+    let mut style = match foreground {
+        Some(colour)  => colour.normal(),
+        None          => Style::default(),
+    };
+
+    for modifier in modifiers {
+        style = match modifier {
+            "bold"       => style.bold(),
+            "dimmed"     => style.dimmed(),
+            "italic"     => style.italic(),
+            "underline"  => style.underline(),
+            "blink"      => style.blink(),
+            "reverse"    => style.reverse(),
+            _            => style.hidden(),
+        };
+    }
+
+    if let Some(colour) = background {
+        style = style.on(colour);
+    }
+
+    Some(style)
+}
+
+/// Parse the name of a basic colour, the number of one of the 256 colours,
+/// or a 24-bit colour.
+fn parse_colour(word: &str) -> Option<Colour> {
+    match word {
+        "black"   => Some(Black),
+        "red"     => Some(Red),
+        "green"   => Some(Green),
+        "yellow"  => Some(Yellow),
+        "blue"    => Some(Blue),
+        "purple"  => Some(Purple),
+        "cyan"    => Some(Cyan),
+        "white"   => Some(White),
+        _ if word.starts_with('#') && word.len() == 7 && word.is_ascii() => {
+            let component = |i: usize| u8::from_str_radix(&word[i .. i + 2], 16).ok();
+            match (component(1), component(3), component(5)) {
+                (Some(r), Some(g), Some(b)) => Some(Fixed(nearest_fixed(r, g, b))),
+                _                           => None,
+            }
+        },
+        _ => word.parse().ok().map(Fixed),
+    }
+}
+
+/// The number of the colour out of the 256 that’s closest to the given
+/// 24-bit colour, looking at both the 6×6×6 cube of colours and the ramp of
+/// greys.
+fn nearest_fixed(r: u8, g: u8, b: u8) -> u8 {
+    const LEVELS: [u8; 6] = [ 0, 95, 135, 175, 215, 255 ];
+
+    let level = |c: u8| LEVELS.iter().enumerate()
+                              .min_by_key(|&(_, &l)| (l as i32 - c as i32).abs())
+                              .map(|(i, _)| i as u8).unwrap_or(0);
+
+    let distance = |(r2, g2, b2): (u8, u8, u8)| {
+        let d = |a: u8, b: u8| (a as i32 - b as i32) * (a as i32 - b as i32);
+        d(r, r2) + d(g, g2) + d(b, b2)
+    };
+
+    let (ri, gi, bi) = (level(r), level(g), level(b));
+    let cube = (LEVELS[ri as usize], LEVELS[gi as usize], LEVELS[bi as usize]);
+
+    let average = (r as u32 + g as u32 + b as u32) / 3;
+    let grey_index = if average < 8 { 0 } else { ::std::cmp::min((average - 3) / 10, 23) as u8 };
+    let grey_level = 8 + 10 * grey_index;
+
+    if distance((grey_level, grey_level, grey_level)) < distance(cube) {
+        232 + grey_index
+    }
+    else {
+        16 + 36 * ri + 6 * gi + bi
+    }
+}
+
+
+#[cfg(test)]
+mod test {
+    use super::{parse_style, nearest_fixed, Theme};
+    use ansi_term::Colour::{Black, Blue, Fixed};
+
+    #[test]
+    fn words() {
+        assert_eq!(parse_style("bold blue on black"), Some(Blue.bold().on(Black)))
+    }
+
+    #[test]
+    fn numbered() {
+        assert_eq!(parse_style("underline 208"), Some(Fixed(208).underline()))
+    }
+
+    #[test]
+    fn rgb() {
+        assert_eq!(nearest_fixed(0xff, 0x87, 0x00), 208);
+        assert_eq!(nearest_fixed(0x80, 0x80, 0x80), 244);
+    }
+
+    #[test]
+    fn not_ascii() {
+        assert_eq!(parse_style("#aébcd"), None)
+    }
+
+    #[test]
+    fn not_a_style() {
+        assert_eq!(parse_style("bold sparkly"), None)
+    }
+
+    #[test]
+    fn tables() {
+        let theme = Theme::parse("punctuation = \"244\"  # grey\n\n[filetypes]\ndirectory = \"bold blue\"\n\n[extensions]\nRS = \"#ffff00\"\n").unwrap();
+        assert_eq!(theme.styles, vec![ (String::new(), "punctuation".to_owned(), Fixed(244).normal()),
+                                       ("filetypes".to_owned(), "directory".to_owned(), Blue.bold()) ]);
+        assert_eq!(theme.names.extensions, vec![ ("rs".to_owned(), Fixed(226).normal()) ]);
+    }
+
+    #[test]
+    fn bad_line() {
+        assert_eq!(Theme::parse("[filetypes]\ndirectory = \"bold sparkly\"").unwrap_err(), "line 2: invalid style")
+    }
+
+    #[test]
+    fn outside_themes_directory() {
+        assert_eq!(Theme::load(Some("../../x")).unwrap_err(), "../../x: invalid theme name")
+    }
+}